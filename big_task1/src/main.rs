@@ -7,244 +7,8 @@ use std::str::FromStr;
 
 use gnuplot::*;
 use interp_util::*;
-
-#[derive(Debug)]
-struct CubicSection {
-    c_begin: f64,
-    c_end: f64,
-
-    l_begin: f64,
-    l_end: f64,
-
-    t_begin: f64,
-    t_end: f64,
-}
-
-impl CubicSection {
-    fn calc(&self, x: f64) -> f64 {
-        self.c_begin * (x - self.t_begin).powi(3) + self.c_end * (self.t_end - x).powi(3) +
-        self.l_begin * (x - self.t_begin) + self.l_end * (self.t_end - x)
-    }
-
-    fn calc_der(&self, x: f64) -> f64 {
-        3.0 * self.c_begin * (x - self.t_begin).powi(2) -
-        3.0 * self.c_end * (self.t_end - x).powi(2) + self.l_begin - self.l_end
-    }
-    fn calc_der2(&self, x: f64) -> f64 {
-        6.0 * self.c_begin * (x - self.t_begin) + 6.0 * self.c_end * (self.t_end - x)
-    }
-}
-
-#[derive(Debug)]
-struct CubicSpline {
-    sections: Vec<CubicSection>,
-    section_bounds: Vec<f64>,
-}
-
-impl CubicSpline {
-    fn calc(&self, x: f64) -> f64 {
-        self.find_section(x).calc(x)
-    }
-    fn calc_der(&self, x: f64) -> f64 {
-        self.find_section(x).calc_der(x)
-    }
-    fn calc_der2(&self, x: f64) -> f64 {
-        self.find_section(x).calc_der2(x)
-    }
-
-    fn find_section(&self, x: f64) -> &CubicSection {
-        for (i, &t) in self.section_bounds.iter().skip(1).enumerate() {
-            if x < t {
-                return &self.sections[i];
-            }
-        }
-
-        &self.sections.last().unwrap()
-    }
-}
-
-// a -- lower diagonal
-// b -- middle
-// c -- upper
-// d -- result column
-fn solve_tridiagonal(a: &[f64], b: &[f64], c: &[f64], d: &[f64]) -> Vec<f64> {
-    let mut cm = Vec::new();
-    let mut dm = Vec::new();
-    cm.push(c[0] / b[0]);
-    dm.push(d[0] / b[0]);
-    for i in 1..c.len() {
-        let tmp_c = c[i] / (b[i] - a[i - 1] * cm[i - 1]);
-        cm.push(tmp_c);
-    }
-    for i in 1..b.len() {
-        let tmp_d = (d[i] - a[i - 1] * dm[i - 1]) / (b[i] - a[i - 1] * cm[i - 1]);
-        dm.push(tmp_d);
-    }
-    let mut res = Vec::new();
-    res.push(*dm.last().unwrap());
-    for i in 1..cm.len() + 1 {
-        let tmp = dm[cm.len() - i] - cm[cm.len() - i] * res[i - 1];
-        res.push(tmp);
-    }
-    res.reverse();
-
-    res
-}
-
-fn create_cubic_spline_natural(pts: &[(f64, f64)]) -> CubicSpline {
-    let y = pts.iter().map(|&(_, y)| y).collect::<Vec<_>>();
-    let t = pts.iter().map(|&(t, _)| t).collect::<Vec<_>>();
-
-    let n = pts.len();
-    let mut h = Vec::new();
-    let mut b = Vec::new();
-    let mut v = Vec::new();
-    let mut u = Vec::new();
-
-    for i in 0..n - 1 {
-        h.push(t[i + 1] - t[i]);
-        b.push((y[i + 1] - y[i]) / h.last().unwrap());
-    }
-    for i in 1..n - 1 {
-        v.push(2.0 * (h[i - 1] + h[i]));
-        u.push(6.0 * (b[i] - b[i - 1]));
-    }
-
-    let sliced_h = &h[1..h.len() - 1];
-    let res = solve_tridiagonal(sliced_h, &v, sliced_h, &u);
-    let mut z = Vec::new();
-    z.push(0.0);
-    z.extend_from_slice(&res);
-    z.push(0.0);
-
-    let mut sections = Vec::new();
-
-    for i in 0..z.len() - 1 {
-        sections.push(CubicSection {
-                          c_begin: z[i + 1] / (6.0 * h[i]),
-                          c_end: z[i] / (6.0 * h[i]),
-                          l_begin: y[i + 1] / h[i] - z[i + 1] * h[i] / 6.0,
-                          l_end: y[i] / h[i] - h[i] * z[i] / 6.0,
-                          t_begin: t[i],
-                          t_end: t[i + 1],
-                      })
-    }
-
-    CubicSpline {
-        sections: sections,
-        section_bounds: t,
-    }
-}
-
-fn create_cubic_spline_clamped(pts: &[(f64, f64)]) -> CubicSpline {
-    let y = pts.iter().map(|&(_, y)| y).collect::<Vec<_>>();
-    let t = pts.iter().map(|&(t, _)| t).collect::<Vec<_>>();
-
-    let n = pts.len();
-    let mut h = Vec::new();
-    let mut b = Vec::new();
-    let mut v = Vec::new();
-    let mut u = Vec::new();
-
-    for i in 0..n - 1 {
-        h.push(t[i + 1] - t[i]);
-        b.push((y[i + 1] - y[i]) / h.last().unwrap());
-    }
-    for i in 1..n - 1 {
-        v.push(2.0 * (h[i - 1] + h[i]));
-        u.push(6.0 * (b[i] - b[i - 1]));
-    }
-
-    v[0] = 1.5 * h[0] + 2.0 * h[1];
-    *v.last_mut().unwrap() = 1.5 * h[h.len() - 2] + 2.0 * h[h.len() - 1];
-    u[0] = u[0] - 3.0 * b[0];
-    *u.last_mut().unwrap() = u[u.len() - 1] + 3.0 * b.last().unwrap();
-
-    let sliced_h = &h[1..h.len() - 1];
-    let res = solve_tridiagonal(sliced_h, &v, sliced_h, &u);
-    let mut z = Vec::new();
-    z.push(0.5 * (6.0 * b[0] / h[0] - res[0]));
-    z.extend_from_slice(&res);
-    z.push(-0.5 * (6.0 * b.last().unwrap() / h.last().unwrap() + res.last().unwrap()));
-
-    let mut sections = Vec::new();
-
-    for i in 0..z.len() - 1 {
-        sections.push(CubicSection {
-                          c_begin: z[i + 1] / (6.0 * h[i]),
-                          c_end: z[i] / (6.0 * h[i]),
-                          l_begin: y[i + 1] / h[i] - z[i + 1] * h[i] / 6.0,
-                          l_end: y[i] / h[i] - h[i] * z[i] / 6.0,
-                          t_begin: t[i],
-                          t_end: t[i + 1],
-                      })
-    }
-
-    CubicSpline {
-        sections: sections,
-        section_bounds: t,
-    }
-}
-
-fn calc_lagrange_polynomial(at_x: f64, pts: &[(f64, f64)]) -> f64 {
-    let lj = |j: usize| -> f64 {
-        let xj = pts[j].0;
-        pts.iter()
-            .map(|&(x, _)| if x == xj { 1.0 } else { (at_x - x) / (xj - x) })
-            .product()
-    };
-    pts.iter()
-        .map(|&pt| pt.1)
-        .zip((0..pts.len()).map(lj))
-        .map(|(yj, lj)| yj * lj)
-        .sum()
-}
-
-fn calc_lagrange_polynomial_der(at_x: f64, pts: &[(f64, f64)]) -> f64 {
-    let lj = |j: usize| -> f64 {
-        let xj = pts[j].0;
-        pts.iter()
-            .map(|&(x, _)| if x == xj { 1.0 } else { (at_x - x) / (xj - x) })
-            .product()
-    };
-    let lj_der = |j: usize| -> f64 {
-        let xj = pts[j].0;
-        let a: f64 = lj(j);
-        let b: f64 = pts.iter()
-            .map(|&(x, _)| if x == xj { 0.0 } else { 1.0 / (at_x - x) })
-            .sum();
-
-        return a * b;
-    };
-    pts.iter()
-        .map(|&pt| pt.1)
-        .zip((0..pts.len()).map(lj_der))
-        .map(|(yj, lj)| yj * lj)
-        .sum()
-}
-
-fn calc_lagrange_polynomial_der2(at_x: f64, pts: &[(f64, f64)]) -> f64 {
-    let prod_exc = |i: usize, l: usize, m: usize| -> f64 {
-        pts.iter().enumerate()
-            .map(|(id, &(x, _))| if id == i || id == l || id == m { 1.0 } else { (at_x - x) / (pts[i].0 - x) })
-            .product()
-    };
-    let sum_exc = |i: usize, l: usize| -> f64 {
-        pts.iter().enumerate()
-            .map(|(id, &(x, _))| if id == i || id == l { 0.0 } else { prod_exc(i, l, id) / (pts[i].0 - x) })
-            .sum()
-    };
-    let lj_der2 = |j: usize| -> f64 {
-        pts.iter().enumerate()
-            .map(|(id, &(x, _))| if id == j { 0.0 } else { sum_exc(j, id) / (pts[j].0 - x) })
-            .sum()
-    };
-    pts.iter()
-        .map(|&pt| pt.1)
-        .zip((0..pts.len()).map(lj_der2))
-        .map(|(yj, lj)| yj * lj)
-        .sum()
-}
+use interp_util::lagrange::*;
+use interp_util::spline::*;
 
 fn main() {
     let x_str = "-2  -1.68421    -1.36842    -1.05263    -0.73684    -0.42105    -0.10526    0.210526    0.526316    0.842105    1.157895    1.473684    1.789474    2.105263    2.421053    2.736842    3.052632    3.368421    3.684211    4";
@@ -268,46 +32,34 @@ fn main() {
         .collect::<Vec<_>>();
 
     let pol_x = linspace(xs[0], xs[xs.len() - 1], 200);
+    let lagrange = LagrangeInterpolant::new(&data).unwrap();
     let pol_y = pol_x
         .iter()
-        .map(|x| calc_lagrange_polynomial(*x, &data))
+        .map(|x| lagrange.calc(*x))
         .collect::<Vec<_>>();
+    let lagrange_der = lagrange.derivative(1);
     let pol_der_y = pol_x
         .iter()
-        .map(|x| calc_lagrange_polynomial_der(*x, &data))
+        .map(|x| lagrange_der.calc(*x))
         .collect::<Vec<_>>();
+    let lagrange_der2 = lagrange.derivative(2);
     let pol_der2_y = pol_x
         .iter()
-        .map(|x| calc_lagrange_polynomial_der2(*x, &data))
+        .map(|x| lagrange_der2.calc(*x))
         .collect::<Vec<_>>();
 
-    let spline = create_cubic_spline_natural(&data);
-    let cub_y = pol_x
-        .iter()
-        .map(|x| spline.calc(*x))
-        .collect::<Vec<_>>();
-    let cub_der_y = pol_x
-        .iter()
-        .map(|x| spline.calc_der(*x))
-        .collect::<Vec<_>>();
-    let cub_der2_y = pol_x
-        .iter()
-        .map(|x| spline.calc_der2(*x))
-        .collect::<Vec<_>>();
+    let spline = create_cubic_spline_natural(&data).unwrap();
+    let cub_y = spline.calc_sorted(&pol_x);
+    let cub_der_y = spline.calc_der_sorted(&pol_x);
+    let cub_der2_y = spline.calc_der2_sorted(&pol_x);
 
-    let spline = create_cubic_spline_clamped(&data);
-    let clamped_cub_y = pol_x
-        .iter()
-        .map(|x| spline.calc(*x))
-        .collect::<Vec<_>>();
-    let clamped_cub_der_y = pol_x
-        .iter()
-        .map(|x| spline.calc_der(*x))
-        .collect::<Vec<_>>();
-    let clamped_cub_der2_y = pol_x
-        .iter()
-        .map(|x| spline.calc_der2(*x))
-        .collect::<Vec<_>>();
+    let spline = create_cubic_spline(&data, Boundary::SecantClamped).unwrap();
+    let clamped_cub_y = spline.calc_sorted(&pol_x);
+    let clamped_cub_der_y = spline.calc_der_sorted(&pol_x);
+    let clamped_cub_der2_y = spline.calc_der2_sorted(&pol_x);
+
+    let pchip_y = create_pchip_spline(&data).unwrap().calc_sorted(&pol_x);
+    let akima_y = create_akima_spline(&data).unwrap().calc_sorted(&pol_x);
 
     plot_line_and_points("cubic"     , "Natural cubic spline"                  , &xs, &ys, &pol_x, &cub_y     );
     plot_line_and_points("cubic_der" , "Natural cubic spline derivative"       , &xs, &ys, &pol_x, &cub_der_y );
@@ -317,6 +69,9 @@ fn main() {
     plot_line_and_points("clamped_cubic_der" , "Clamped cubic spline derivative"       , &xs, &ys, &pol_x, &clamped_cub_der_y );
     plot_line_and_points("clamped_cubic_der2", "Clamped cubic spline second derivative", &xs, &ys, &pol_x, &clamped_cub_der2_y);
 
+    plot_line_and_points("pchip", "Monotone cubic (PCHIP)", &xs, &ys, &pol_x, &pchip_y);
+    plot_line_and_points("akima", "Akima spline"          , &xs, &ys, &pol_x, &akima_y);
+
     plot_line_and_points("lagrange"     , "Lagrange poly"                  , &xs, &ys, &pol_x, &pol_y     );
     plot_line_and_points("lagrange_der" , "Lagrange poly derivative"       , &xs, &ys, &pol_x, &pol_der_y );
     plot_line_and_points("lagrange_der2", "Lagrange poly second derivative", &xs, &ys, &pol_x, &pol_der2_y);
@@ -0,0 +1,46 @@
+extern crate interp_util;
+
+use std::time::Instant;
+
+use interp_util::linspace;
+use interp_util::spline::*;
+
+// The section lookup CubicSpline used before the binary search.
+fn calc_linear_scan(spline: &CubicSpline, x: f64) -> f64 {
+    for (i, &t) in spline.knots().iter().skip(1).enumerate() {
+        if x < t {
+            return spline.sections()[i].calc(x);
+        }
+    }
+
+    spline.sections().last().unwrap().calc(x)
+}
+
+fn report(name: &str, points: usize, start: Instant, checksum: f64) {
+    let ns = start.elapsed().as_nanos() as f64;
+    println!("{:<14} {:>8} points {:>12.1} ns/point  (checksum {:.6})", name, points, ns / points as f64, checksum);
+}
+
+fn main() {
+    let knots = 100_000;
+    let pts = linspace(0.0, 1000.0, knots)
+        .into_iter()
+        .map(|x| (x, (0.05 * x).sin() + 0.01 * x))
+        .collect::<Vec<_>>();
+    let spline = create_cubic_spline_natural(&pts).unwrap();
+    println!("Spline with {} knots", knots);
+
+    let scan_xs = linspace(0.0, 1000.0, 2_000);
+    let start = Instant::now();
+    let checksum = scan_xs.iter().map(|&x| calc_linear_scan(&spline, x)).sum();
+    report("linear scan", scan_xs.len(), start, checksum);
+
+    let xs = linspace(0.0, 1000.0, 1_000_000);
+    let start = Instant::now();
+    let checksum = xs.iter().map(|&x| spline.calc(x)).sum();
+    report("binary search", xs.len(), start, checksum);
+
+    let start = Instant::now();
+    let checksum = spline.calc_sorted(&xs).iter().sum();
+    report("sorted cursor", xs.len(), start, checksum);
+}
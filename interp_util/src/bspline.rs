@@ -0,0 +1,618 @@
+use std::error::Error;
+use std::fmt;
+
+use linalg::SymmetricBanded;
+use spline::{check_weights, from_sections, CubicSection, CubicSpline, SplineError};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum KnotError {
+    // A degree p spline needs at least 2*(p + 1) knots.
+    TooFewKnots { needed: usize, found: usize },
+    NotFinite { index: usize },
+    // knots[index] is less than knots[index - 1].
+    Decreasing { index: usize },
+    // A knot repeated more than degree + 1 times.
+    Multiplicity { x: f64, multiplicity: usize },
+    // knots[degree] == knots[len - degree - 1].
+    EmptyDomain,
+    CoefficientCount { expected: usize, found: usize },
+    OutsideDomain { x: f64 },
+    // Conversion to cubic sections needs degree <= 3.
+    DegreeTooHigh { degree: usize },
+}
+
+impl fmt::Display for KnotError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            KnotError::TooFewKnots { needed, found } => write!(f, "need at least {} knots, found {}", needed, found),
+            KnotError::NotFinite { index } => write!(f, "knot #{} is not finite", index),
+            KnotError::Decreasing { index } => write!(f, "knot #{} is less than the previous one", index),
+            KnotError::Multiplicity { x, multiplicity } => write!(f, "knot {} repeats {} times", x, multiplicity),
+            KnotError::EmptyDomain => write!(f, "empty spline domain"),
+            KnotError::CoefficientCount { expected, found } => {
+                write!(f, "expected {} coefficients, found {}", expected, found)
+            }
+            KnotError::OutsideDomain { x } => write!(f, "{} is outside of the spline domain", x),
+            KnotError::DegreeTooHigh { degree } => write!(f, "degree {} is above 3", degree),
+        }
+    }
+}
+
+impl Error for KnotError {}
+
+// Nondecreasing knots t[0..m] of a degree p spline with n = m - p - 1 basis
+// functions. The domain is [t[p], t[n]].
+#[derive(Debug, Clone)]
+pub struct KnotVector {
+    knots: Vec<f64>,
+    degree: usize,
+}
+
+impl KnotVector {
+    pub fn new(knots: Vec<f64>, degree: usize) -> Result<KnotVector, KnotError> {
+        let needed = 2 * (degree + 1);
+        if knots.len() < needed {
+            return Err(KnotError::TooFewKnots { needed, found: knots.len() });
+        }
+        if let Some(index) = knots.iter().position(|t| !t.is_finite()) {
+            return Err(KnotError::NotFinite { index });
+        }
+        if let Some(index) = (1..knots.len()).find(|&i| knots[i] < knots[i - 1]) {
+            return Err(KnotError::Decreasing { index });
+        }
+        let mut first = 0;
+        for i in 1..knots.len() + 1 {
+            if i == knots.len() || knots[i] != knots[first] {
+                if i - first > degree + 1 {
+                    return Err(KnotError::Multiplicity { x: knots[first], multiplicity: i - first });
+                }
+                first = i;
+            }
+        }
+        if knots[degree] == knots[knots.len() - degree - 1] {
+            return Err(KnotError::EmptyDomain);
+        }
+
+        Ok(KnotVector { knots, degree })
+    }
+
+    // Breakpoints with both ends repeated degree + 1 times, so the spline
+    // starts and ends at its first and last coefficients.
+    pub fn clamped(breakpoints: &[f64], degree: usize) -> Result<KnotVector, KnotError> {
+        if breakpoints.len() < 2 {
+            return Err(KnotError::TooFewKnots { needed: 2, found: breakpoints.len() });
+        }
+        let mut knots = vec![breakpoints[0]; degree];
+        knots.extend_from_slice(breakpoints);
+        knots.extend(vec![breakpoints[breakpoints.len() - 1]; degree]);
+        KnotVector::new(knots, degree)
+    }
+
+    // Clamped knots on `intervals` equal pieces of [a, b].
+    pub fn uniform(a: f64, b: f64, intervals: usize, degree: usize) -> Result<KnotVector, KnotError> {
+        KnotVector::clamped(&::linspace(a, b, intervals + 1), degree)
+    }
+
+    pub fn knots(&self) -> &[f64] {
+        &self.knots
+    }
+
+    pub fn degree(&self) -> usize {
+        self.degree
+    }
+
+    pub fn basis_count(&self) -> usize {
+        self.knots.len() - self.degree - 1
+    }
+
+    pub fn domain(&self) -> (f64, f64) {
+        (self.knots[self.degree], self.knots[self.basis_count()])
+    }
+
+    // Index mu of the nonempty knot span [t[mu], t[mu + 1]) holding x, with
+    // p <= mu < n. Points outside of the domain go to the first or last span.
+    pub fn find_span(&self, x: f64) -> usize {
+        let (p, n) = (self.degree, self.basis_count());
+        let t = &self.knots;
+        let mut mu = t.partition_point(|&k| k <= x).max(p + 1).min(n) - 1;
+        while mu > p && t[mu] == t[mu + 1] {
+            mu -= 1;
+        }
+        while t[mu] == t[mu + 1] {
+            mu += 1;
+        }
+
+        mu
+    }
+
+    // The p + 1 basis functions that may be nonzero at x, N[first..first+p+1],
+    // by the Cox-de Boor recurrence; returns (first, values).
+    pub fn basis(&self, x: f64) -> (usize, Vec<f64>) {
+        let mu = self.find_span(x);
+        (mu - self.degree, self.basis_in_span(mu, x))
+    }
+
+    // All n basis functions at x.
+    pub fn basis_all(&self, x: f64) -> Vec<f64> {
+        let (first, values) = self.basis(x);
+        let mut res = vec![0.0; self.basis_count()];
+        res[first..first + values.len()].copy_from_slice(&values);
+        res
+    }
+
+    fn basis_in_span(&self, mu: usize, x: f64) -> Vec<f64> {
+        let (p, t) = (self.degree, &self.knots);
+        let mut n = vec![0.0; p + 1];
+        let mut left = vec![0.0; p + 1];
+        let mut right = vec![0.0; p + 1];
+        n[0] = 1.0;
+        for j in 1..p + 1 {
+            left[j] = x - t[mu + 1 - j];
+            right[j] = t[mu + j] - x;
+            let mut saved = 0.0;
+            for r in 0..j {
+                let tmp = n[r] / (right[r + 1] + left[j - r]);
+                n[r] = saved + right[r + 1] * tmp;
+                saved = left[j - r] * tmp;
+            }
+            n[j] = saved;
+        }
+
+        n
+    }
+}
+
+// S(x) = sum(c[i]*N[i](x)). Outside of the domain the polynomial of the first
+// or the last span is continued.
+#[derive(Debug, Clone)]
+pub struct BSpline {
+    knots: KnotVector,
+    coefs: Vec<f64>,
+}
+
+impl BSpline {
+    pub fn new(knots: KnotVector, coefs: Vec<f64>) -> Result<BSpline, KnotError> {
+        if coefs.len() != knots.basis_count() {
+            return Err(KnotError::CoefficientCount { expected: knots.basis_count(), found: coefs.len() });
+        }
+
+        Ok(BSpline { knots, coefs })
+    }
+
+    pub fn knots(&self) -> &KnotVector {
+        &self.knots
+    }
+
+    pub fn degree(&self) -> usize {
+        self.knots.degree
+    }
+
+    pub fn coefficients(&self) -> &[f64] {
+        &self.coefs
+    }
+
+    // de Boor's algorithm.
+    pub fn calc(&self, x: f64) -> f64 {
+        self.calc_in_span(self.knots.find_span(x), x)
+    }
+
+    pub fn calc_der(&self, x: f64) -> f64 {
+        self.calc_nth_der(1, x)
+    }
+
+    pub fn calc_der2(&self, x: f64) -> f64 {
+        self.calc_nth_der(2, x)
+    }
+
+    pub fn calc_nth_der(&self, order: usize, x: f64) -> f64 {
+        self.derivative(order).calc(x)
+    }
+
+    // The derivative of the given order as a B-spline: differentiating drops
+    // the degree by one, with c'[i] = p*(c[i + 1] - c[i])/(t[i + p + 1] - t[i + 1])
+    // on the knots without the first and the last one.
+    pub fn derivative(&self, order: usize) -> BSpline {
+        let mut res = self.clone();
+        for _ in 0..order {
+            res = res.derivative_once();
+        }
+        res
+    }
+
+    fn derivative_once(&self) -> BSpline {
+        let (p, t) = (self.knots.degree, &self.knots.knots);
+        if p == 0 {
+            return BSpline {
+                knots: self.knots.clone(),
+                coefs: vec![0.0; self.coefs.len()],
+            };
+        }
+
+        let coefs = self.coefs
+            .windows(2)
+            .enumerate()
+            .map(|(i, c)| {
+                let h = t[i + p + 1] - t[i + 1];
+                if h > 0.0 { p as f64 * (c[1] - c[0]) / h } else { 0.0 }
+            })
+            .collect();
+
+        BSpline {
+            knots: KnotVector {
+                knots: t[1..t.len() - 1].to_vec(),
+                degree: p - 1,
+            },
+            coefs,
+        }
+    }
+
+    // Inserts x into the knots without changing the curve (Boehm's algorithm).
+    pub fn insert_knot(&mut self, x: f64) -> Result<(), KnotError> {
+        let (a, b) = self.knots.domain();
+        if x.is_nan() || x < a || x > b {
+            return Err(KnotError::OutsideDomain { x });
+        }
+        let (p, t) = (self.knots.degree, &self.knots.knots);
+        let multiplicity = t.iter().filter(|&&k| k == x).count();
+        if multiplicity > p {
+            return Err(KnotError::Multiplicity { x, multiplicity: multiplicity + 1 });
+        }
+
+        let mu = self.knots.find_span(x);
+        let mut coefs = Vec::with_capacity(self.coefs.len() + 1);
+        coefs.extend_from_slice(&self.coefs[..mu + 1 - p]);
+        for i in mu + 1 - p..mu + 1 {
+            let alpha = (x - t[i]) / (t[i + p] - t[i]);
+            coefs.push((1.0 - alpha) * self.coefs[i - 1] + alpha * self.coefs[i]);
+        }
+        coefs.extend_from_slice(&self.coefs[mu..]);
+
+        self.knots.knots.insert(mu + 1, x);
+        self.coefs = coefs;
+
+        Ok(())
+    }
+
+    // Piecewise form with one CubicSection per nonempty knot span of the
+    // domain; needs degree <= 3.
+    pub fn to_cubic_spline(&self) -> Result<CubicSpline, KnotError> {
+        if self.degree() > 3 {
+            return Err(KnotError::DegreeTooHigh { degree: self.degree() });
+        }
+
+        let der = self.derivative(1);
+        let t = &self.knots.knots;
+        let sections = (self.knots.degree..self.knots.basis_count())
+            .filter(|&mu| t[mu] < t[mu + 1])
+            .map(|mu| {
+                let (a, b) = (t[mu], t[mu + 1]);
+                let der_mu = der.knots.find_span(0.5 * (a + b));
+                CubicSection::from_hermite(a,
+                                           b,
+                                           self.calc_in_span(mu, a),
+                                           self.calc_in_span(mu, b),
+                                           der.calc_in_span(der_mu, a),
+                                           der.calc_in_span(der_mu, b))
+            })
+            .collect();
+
+        Ok(from_sections(sections))
+    }
+
+    fn calc_in_span(&self, mu: usize, x: f64) -> f64 {
+        let (p, t) = (self.knots.degree, &self.knots.knots);
+        let mut d = self.coefs[mu - p..mu + 1].to_vec();
+        for r in 1..p + 1 {
+            for j in (r..p + 1).rev() {
+                let alpha = (x - t[j + mu - p]) / (t[j + 1 + mu - r] - t[j + mu - p]);
+                d[j] = (1.0 - alpha) * d[j - 1] + alpha * d[j];
+            }
+        }
+
+        d[p]
+    }
+}
+
+// Cubic B-spline equal to the spline: each breakpoint gets multiplicity 1, 2
+// or 3 depending on whether the spline is C2, C1 or only C0 there, and every
+// coefficient is the blossom of a section polynomial at t[i+1], t[i+2], t[i+3].
+pub fn from_cubic_spline(spline: &CubicSpline) -> BSpline {
+    let sections = spline.sections();
+    let bounds = spline.knots();
+
+    let mut knots = vec![bounds[0]; 4];
+    for (k, w) in sections.windows(2).enumerate() {
+        let x = bounds[k + 1];
+        let close = |l: f64, r: f64| (l - r).abs() <= 1e-10 * l.abs().max(r.abs()).max(1.0);
+        let multiplicity = if !close(w[0].calc_der(x), w[1].calc_der(x)) {
+            3
+        } else if !close(w[0].calc_der2(x), w[1].calc_der2(x)) {
+            2
+        } else {
+            1
+        };
+        knots.extend(vec![x; multiplicity]);
+    }
+    knots.extend(vec![bounds[bounds.len() - 1]; 4]);
+
+    // Section of the first nonempty span where N[i] is nonzero.
+    let coefs = (0..knots.len() - 4)
+        .map(|i| {
+            let mu = (i..i + 4).find(|&mu| knots[mu] < knots[mu + 1]).unwrap();
+            let section = &sections[bounds.partition_point(|&b| b <= knots[mu]) - 1];
+            blossom(section, knots[i + 1], knots[i + 2], knots[i + 3])
+        })
+        .collect();
+
+    BSpline {
+        knots: KnotVector { knots, degree: 3 },
+        coefs,
+    }
+}
+
+// Polar form of the section cubic, from its Bezier points by de Casteljau
+// steps at different parameters.
+fn blossom(section: &CubicSection, u1: f64, u2: f64, u3: f64) -> f64 {
+    let (a, b) = (section.t_begin(), section.t_end());
+    let h = b - a;
+    let (ya, yb) = (section.calc(a), section.calc(b));
+    let mut pts = vec![ya, ya + h * section.calc_der(a) / 3.0, yb - h * section.calc_der(b) / 3.0, yb];
+    for &u in &[u1, u2, u3] {
+        let s = (u - a) / h;
+        pts = pts.windows(2).map(|w| (1.0 - s) * w[0] + s * w[1]).collect();
+    }
+
+    pts[0]
+}
+
+// Weighted least-squares B-spline on fixed knots.
+#[derive(Debug, Clone)]
+pub struct SplineFit {
+    spline: BSpline,
+    residuals: Vec<f64>,
+    rss: f64,
+}
+
+impl SplineFit {
+    pub fn spline(&self) -> &BSpline {
+        &self.spline
+    }
+
+    pub fn into_spline(self) -> BSpline {
+        self.spline
+    }
+
+    pub fn calc(&self, x: f64) -> f64 {
+        self.spline.calc(x)
+    }
+
+    // y[i] - S(x[i]) in the order of the input points.
+    pub fn residuals(&self) -> &[f64] {
+        &self.residuals
+    }
+
+    // Weighted residual sum of squares.
+    pub fn rss(&self) -> f64 {
+        self.rss
+    }
+}
+
+// Minimizes sum(w[i]*(y[i] - S(x[i]))^2) over the coefficients of a spline
+// on the given knots. The normal equations B^T*W*B*c = B^T*W*y are banded
+// with bandwidth p and solved by LDL^T; they are singular unless every basis
+// function has its own data point inside its support (Schoenberg-Whitney).
+// Repeated abscissae are fine; all points must lie in the knot domain.
+pub fn fit_bspline(pts: &[(f64, f64)], weights: Option<&[f64]>, knots: KnotVector) -> Result<SplineFit, SplineError> {
+    let n = knots.basis_count();
+    if pts.len() < n {
+        return Err(SplineError::TooFewPoints { needed: n, found: pts.len() });
+    }
+    if let Some(index) = pts.iter().position(|&(x, y)| !x.is_finite() || !y.is_finite()) {
+        return Err(SplineError::NotFinite { index });
+    }
+    check_weights(weights, pts.len())?;
+    let (a, b) = knots.domain();
+    if let Some(&(x, _)) = pts.iter().find(|&&(x, _)| x < a || x > b) {
+        return Err(KnotError::OutsideDomain { x }.into());
+    }
+
+    let mut normal = SymmetricBanded::zeros(n, knots.degree);
+    let mut rhs = vec![0.0; n];
+    for (i, &(x, y)) in pts.iter().enumerate() {
+        let w = weights.map_or(1.0, |w| w[i]);
+        let (first, values) = knots.basis(x);
+        for (r, &vr) in values.iter().enumerate() {
+            rhs[first + r] += w * vr * y;
+            for (s, &vs) in values.iter().enumerate().skip(r) {
+                normal.add(first + r, first + s, w * vr * vs);
+            }
+        }
+    }
+    let coefs = normal.ldl()?.solve(&rhs)?;
+
+    let spline = BSpline { knots, coefs };
+    let residuals = pts.iter().map(|&(x, y)| y - spline.calc(x)).collect::<Vec<_>>();
+    let rss = residuals.iter().enumerate().map(|(i, r)| weights.map_or(1.0, |w| w[i]) * r * r).sum();
+
+    Ok(SplineFit { spline, residuals, rss })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use spline::{create_cubic_spline_natural, create_pchip_spline};
+
+    fn sample_spline() -> BSpline {
+        let knots = KnotVector::new(vec![0.0, 0.0, 0.0, 0.0, 0.5, 1.5, 1.5, 2.0, 3.0, 3.0, 3.0, 3.0], 3).unwrap();
+        BSpline::new(knots, vec![1.0, -0.5, 2.0, 0.3, 1.1, -1.0, 0.7, 2.0]).unwrap()
+    }
+
+    #[test]
+    fn basis_partition_of_unity() {
+        for degree in 1..5 {
+            let knots = KnotVector::new(vec![-1.0, -1.0, 0.0, 0.3, 0.3, 0.9, 1.2, 2.0, 2.5, 3.0, 3.5, 3.5], degree).unwrap();
+            let (a, b) = knots.domain();
+            for &x in &::linspace(a, b, 37) {
+                let values = knots.basis_all(x);
+                assert!((values.iter().sum::<f64>() - 1.0).abs() < 1e-13);
+                assert!(values.iter().all(|&v| v >= 0.0));
+                assert!(values.iter().filter(|&&v| v != 0.0).count() <= degree + 1);
+            }
+        }
+    }
+
+    #[test]
+    fn uniform_quadratic_basis() {
+        // The quadratic B-spline on the integer knots 2, 3, 4, 5 is 1/8, 3/4
+        // and 1/8 at the midpoints of its pieces.
+        let knots = KnotVector::new((0..8).map(|i| i as f64).collect(), 2).unwrap();
+        let n = |x: f64| knots.basis_all(x)[2];
+        assert!((n(2.5) - 0.125).abs() < 1e-15);
+        assert!((n(3.5) - 0.75).abs() < 1e-15);
+        assert!((n(4.5) - 0.125).abs() < 1e-15);
+        assert_eq!(knots.basis_all(4.5)[0], 0.0);
+        assert_eq!(knots.find_span(2.0), 2);
+        assert_eq!(knots.find_span(5.0), 4);
+        assert_eq!(knots.find_span(-3.0), 2);
+    }
+
+    #[test]
+    fn de_boor_matches_basis_sum() {
+        let spline = sample_spline();
+        for &x in &::linspace(0.0, 3.0, 31) {
+            let sum = spline.knots().basis_all(x).iter().zip(spline.coefficients()).map(|(n, c)| n * c).sum::<f64>();
+            assert!((spline.calc(x) - sum).abs() < 1e-13);
+        }
+        assert_eq!(spline.calc(0.0), 1.0);
+        assert!((spline.calc(3.0) - 2.0).abs() < 1e-14);
+    }
+
+    #[test]
+    fn derivatives() {
+        let spline = sample_spline();
+        let eps = 1e-6;
+        for &x in &[0.1, 0.7, 1.2, 1.7, 2.4, 2.9] {
+            let fd = (spline.calc(x + eps) - spline.calc(x - eps)) / (2.0 * eps);
+            assert!((spline.calc_der(x) - fd).abs() < 1e-6);
+            let fd2 = (spline.calc_der(x + eps) - spline.calc_der(x - eps)) / (2.0 * eps);
+            assert!((spline.calc_der2(x) - fd2).abs() < 1e-5);
+        }
+        assert_eq!(spline.derivative(4).calc(1.0), 0.0);
+    }
+
+    #[test]
+    fn insert_knot_keeps_curve() {
+        let mut spline = sample_spline();
+        let before = ::linspace(0.0, 3.0, 41).iter().map(|&x| spline.calc(x)).collect::<Vec<_>>();
+        for &x in &[0.25, 1.0, 1.5, 2.0, 2.0, 2.0, 2.5] {
+            spline.insert_knot(x).unwrap();
+        }
+        assert_eq!(spline.coefficients().len(), 15);
+        for (&x, y) in ::linspace(0.0, 3.0, 41).iter().zip(before) {
+            assert!((spline.calc(x) - y).abs() < 1e-12);
+        }
+        assert_eq!(spline.insert_knot(2.0), Err(KnotError::Multiplicity { x: 2.0, multiplicity: 5 }));
+        assert_eq!(spline.insert_knot(3.5), Err(KnotError::OutsideDomain { x: 3.5 }));
+    }
+
+    #[test]
+    fn cubic_sections_roundtrip() {
+        let spline = sample_spline();
+        let cubic = spline.to_cubic_spline().unwrap();
+        assert_eq!(cubic.knots(), &[0.0, 0.5, 1.5, 2.0, 3.0]);
+        for &x in &::linspace(-0.5, 3.5, 41) {
+            assert!((cubic.calc(x) - spline.calc(x)).abs() < 1e-12);
+            assert!((cubic.calc_der(x) - spline.calc_der(x)).abs() < 1e-11);
+        }
+
+        let back = from_cubic_spline(&cubic);
+        assert_eq!(back.knots().knots(), spline.knots().knots());
+        for (a, b) in back.coefficients().iter().zip(spline.coefficients()) {
+            assert!((a - b).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn from_interpolating_splines() {
+        let pts = [(0.0, 1.0), (0.5, 2.0), (1.3, 0.5), (2.0, 1.5), (3.1, 3.0)];
+
+        // C2 spline: simple interior knots.
+        let natural = create_cubic_spline_natural(&pts).unwrap();
+        let b = from_cubic_spline(&natural);
+        assert_eq!(b.coefficients().len(), pts.len() + 2);
+        for &x in &::linspace(0.0, 3.1, 50) {
+            assert!((b.calc(x) - natural.calc(x)).abs() < 1e-12);
+        }
+
+        // PCHIP is only C1: double knots.
+        let pchip = create_pchip_spline(&pts).unwrap();
+        let b = from_cubic_spline(&pchip);
+        assert_eq!(b.coefficients().len(), 2 * pts.len());
+        for &x in &::linspace(0.0, 3.1, 50) {
+            assert!((b.calc(x) - pchip.calc(x)).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn least_squares_reproduces_cubic() {
+        let poly = |x: f64| ((0.5 * x - 1.0) * x + 2.0) * x - 3.0;
+        let pts = ::linspace(-1.0, 2.0, 40).iter().map(|&x| (x, poly(x))).collect::<Vec<_>>();
+        let fit = fit_bspline(&pts, None, KnotVector::uniform(-1.0, 2.0, 5, 3).unwrap()).unwrap();
+        assert!(fit.rss() < 1e-20);
+        for &x in &[-1.0, -0.3, 0.8, 2.0] {
+            assert!((fit.calc(x) - poly(x)).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn least_squares_noisy_series() {
+        // Deterministic zero-mean noise in [-0.5, 0.5).
+        let noise = |i: usize| ((i * 7919 + 13) % 101) as f64 / 101.0 - 0.5;
+        let f = |x: f64| (3.0 * x).sin() * (-0.2 * x).exp();
+        let pts = ::linspace(0.0, 10.0, 1000).iter().enumerate().map(|(i, &x)| (x, f(x) + 0.2 * noise(i))).collect::<Vec<_>>();
+        let fit = fit_bspline(&pts, None, KnotVector::uniform(0.0, 10.0, 30, 3).unwrap()).unwrap();
+
+        assert_eq!(fit.residuals().len(), pts.len());
+        assert!((fit.rss() - fit.residuals().iter().map(|r| r * r).sum::<f64>()).abs() < 1e-12);
+        for &x in &::linspace(0.0, 10.0, 57) {
+            assert!((fit.calc(x) - f(x)).abs() < 0.03, "S({}) = {} vs {}", x, fit.calc(x), f(x));
+        }
+    }
+
+    #[test]
+    fn least_squares_weights() {
+        let pts = [(0.0, 0.0), (1.0, 1.0), (2.0, 0.0), (3.0, 1.0)];
+        let knots = KnotVector::uniform(0.0, 3.0, 1, 1).unwrap();
+        // The line through the heavily weighted first two points.
+        let fit = fit_bspline(&pts, Some(&[1e9, 1e9, 1.0, 1.0]), knots.clone()).unwrap();
+        assert!((fit.calc(3.0) - 3.0).abs() < 1e-6);
+        assert!(fit.residuals()[0].abs() < 1e-6);
+
+        assert_eq!(fit_bspline(&pts, Some(&[1.0, 1.0]), knots.clone()).unwrap_err(), SplineError::InvalidWeights);
+        assert_eq!(fit_bspline(&[(0.0, 1.0), (4.0, 1.0)], None, knots.clone()).unwrap_err(),
+                   SplineError::Knots(KnotError::OutsideDomain { x: 4.0 }));
+        assert_eq!(fit_bspline(&pts[..1], None, knots).unwrap_err(), SplineError::TooFewPoints { needed: 2, found: 1 });
+    }
+
+    #[test]
+    fn least_squares_needs_data_in_every_support() {
+        let pts = ::linspace(0.0, 0.9, 10).iter().map(|&x| (x, x)).collect::<Vec<_>>();
+        let knots = KnotVector::uniform(0.0, 3.0, 3, 1).unwrap();
+        match fit_bspline(&pts, None, knots) {
+            Err(SplineError::Linalg(_)) => (),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn invalid_knots() {
+        assert_eq!(KnotVector::new(vec![0.0, 1.0, 2.0], 1).unwrap_err(), KnotError::TooFewKnots { needed: 4, found: 3 });
+        assert_eq!(KnotVector::new(vec![0.0, 1.0, 0.5, 2.0], 1).unwrap_err(), KnotError::Decreasing { index: 2 });
+        assert_eq!(KnotVector::new(vec![0.0, 1.0, 1.0, 1.0, 2.0], 1).unwrap_err(),
+                   KnotError::Multiplicity { x: 1.0, multiplicity: 3 });
+        assert_eq!(KnotVector::new(vec![0.0, 1.0, 1.0, 2.0], 1).unwrap_err(), KnotError::EmptyDomain);
+        assert_eq!(KnotVector::new(vec![0.0, f64::NAN, 1.0, 2.0], 1).unwrap_err(), KnotError::NotFinite { index: 1 });
+        let knots = KnotVector::uniform(0.0, 1.0, 4, 2).unwrap();
+        assert_eq!(BSpline::new(knots, vec![0.0; 5]).unwrap_err(), KnotError::CoefficientCount { expected: 6, found: 5 });
+    }
+}
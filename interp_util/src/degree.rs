@@ -0,0 +1,223 @@
+use std::fmt;
+
+use lsq::{FitError, LeastSquares};
+use stats::f_sf;
+
+// How select_degree compares the candidate degrees. With n points, k = d + 1
+// coefficients and the weighted residual sum of squares rss:
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Criterion {
+    // n*ln(rss/n) + 2k, smallest wins.
+    Aic,
+    // n*ln(rss/n) + k*ln(n), smallest wins.
+    Bic,
+    // Mean weighted squared prediction error over `folds` folds, point i in
+    // fold i % folds; smallest wins. Needs 2 <= folds <= n.
+    CrossValidation { folds: usize },
+    // Cross-validation with every point in its own fold.
+    LeaveOneOut,
+    // p-value of the F-test for the term added at each degree; the degree is
+    // raised while the new term is significant at level alpha.
+    FTest { alpha: f64 },
+}
+
+impl fmt::Display for Criterion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Criterion::Aic => write!(f, "AIC"),
+            Criterion::Bic => write!(f, "BIC"),
+            Criterion::CrossValidation { folds } => write!(f, "{}-fold CV", folds),
+            Criterion::LeaveOneOut => write!(f, "LOO CV"),
+            Criterion::FTest { alpha } => write!(f, "F-test p (alpha {})", alpha),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DegreeScore {
+    pub degree: usize,
+    pub rss: f64,
+    // Value of the criterion; NaN for degree 0 with the F-test.
+    pub score: f64,
+}
+
+#[derive(Debug, Clone)]
+pub struct DegreeSelection {
+    criterion: Criterion,
+    scores: Vec<DegreeScore>,
+    best: usize,
+}
+
+impl DegreeSelection {
+    pub fn criterion(&self) -> Criterion {
+        self.criterion
+    }
+
+    pub fn scores(&self) -> &[DegreeScore] {
+        &self.scores
+    }
+
+    pub fn best_degree(&self) -> usize {
+        self.best
+    }
+}
+
+// Table of degree, rss and criterion, the chosen degree marked with '*'.
+impl fmt::Display for DegreeSelection {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{:>6}  {:>14}  {:>14}", "degree", "rss", self.criterion.to_string())?;
+        for s in &self.scores {
+            let mark = if s.degree == self.best { '*' } else { ' ' };
+            writeln!(f, "{:>5}{}  {:>14.6e}  {:>14.6e}", s.degree, mark, s.rss, s.score)?;
+        }
+        Ok(())
+    }
+}
+
+// Fits basis(d) for d = 0..max_degree and picks a degree by the criterion.
+// basis(d) is expected to have d + 1 functions, e.g. |d| legendre_basis(d, a, b).
+pub fn select_degree<'a, F>(basis: F, pts: &[(f64, f64)], weights: Option<&[f64]>, max_degree: usize, criterion: Criterion) -> Result<DegreeSelection, FitError>
+    where F: Fn(usize) -> LeastSquares<'a>
+{
+    let n = pts.len();
+    if let Criterion::CrossValidation { folds } = criterion {
+        if folds < 2 || folds > n {
+            return Err(FitError::InvalidFolds { folds, points: n });
+        }
+    }
+    let mut scores: Vec<DegreeScore> = Vec::with_capacity(max_degree + 1);
+    for degree in 0..max_degree + 1 {
+        let lsq = basis(degree);
+        let rss = lsq.fit(pts, weights)?.rss();
+        let k = (degree + 1) as f64;
+        let score = match criterion {
+            Criterion::Aic => n as f64 * (rss / n as f64).ln() + 2.0 * k,
+            Criterion::Bic => n as f64 * (rss / n as f64).ln() + k * (n as f64).ln(),
+            Criterion::CrossValidation { folds } => cross_validate(&lsq, pts, weights, folds)?,
+            Criterion::LeaveOneOut => cross_validate(&lsq, pts, weights, n)?,
+            Criterion::FTest { .. } if degree == 0 => f64::NAN,
+            Criterion::FTest { .. } => {
+                let dof = n as f64 - k;
+                f_sf((scores[degree - 1].rss - rss) / (rss / dof), 1.0, dof)
+            }
+        };
+        scores.push(DegreeScore { degree, rss, score });
+    }
+
+    let best = match criterion {
+        Criterion::FTest { alpha } => scores.iter().skip(1).take_while(|s| s.score < alpha).count(),
+        _ => {
+            scores.iter()
+                .filter(|s| !s.score.is_nan())
+                .fold(None, |best: Option<&DegreeScore>, s| match best {
+                    Some(b) if b.score <= s.score => Some(b),
+                    _ => Some(s),
+                })
+                .map_or(0, |s| s.degree)
+        }
+    };
+
+    Ok(DegreeSelection { criterion, scores, best })
+}
+
+fn cross_validate(lsq: &LeastSquares, pts: &[(f64, f64)], weights: Option<&[f64]>, folds: usize) -> Result<f64, FitError> {
+    let weight = |i: usize| weights.map_or(1.0, |w| w[i]);
+    let mut err = 0.0;
+    for fold in 0..folds {
+        let train = (0..pts.len()).filter(|i| i % folds != fold).collect::<Vec<_>>();
+        let train_pts = train.iter().map(|&i| pts[i]).collect::<Vec<_>>();
+        let train_w = train.iter().map(|&i| weight(i)).collect::<Vec<_>>();
+        let fit = lsq.fit(&train_pts, Some(&train_w))?;
+        for i in (fold..pts.len()).step_by(folds) {
+            err += weight(i) * (pts[i].1 - fit.calc(pts[i].0)).powi(2);
+        }
+    }
+
+    Ok(err / (0..pts.len()).map(weight).sum::<f64>())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use orthopoly::legendre_basis;
+
+    // Deterministic noise in (-0.5, 0.5). The crate's tests::noise does not
+    // do here: it steps by 41/101 from one index to the next, so by only
+    // 3/101 across every fifth point, and each of the interleaved 5-fold CV
+    // folds would see a smooth ramp that a degree 8 fit predicts best.
+    fn noise(i: usize) -> f64 {
+        let v = (i as f64 * 12.9898 + 1.0).sin() * 43_758.545_3;
+        v - v.floor() - 0.5
+    }
+
+    // Noisy cubic on [0, 5].
+    fn cubic_data() -> Vec<(f64, f64)> {
+        ::linspace(0.0, 5.0, 80)
+            .iter()
+            .enumerate()
+            .map(|(i, &x)| (x, 0.3 * x * x * x - 2.0 * x * x + 3.0 * x - 1.0 + 0.5 * noise(i)))
+            .collect()
+    }
+
+    #[test]
+    fn criteria_find_cubic() {
+        let pts = cubic_data();
+        let basis = |d| legendre_basis(d, 0.0, 5.0);
+        for &criterion in &[Criterion::Aic,
+                            Criterion::Bic,
+                            Criterion::CrossValidation { folds: 5 },
+                            Criterion::LeaveOneOut,
+                            Criterion::FTest { alpha: 0.01 }] {
+            let selection = select_degree(basis, &pts, None, 8, criterion).unwrap();
+            assert_eq!(selection.best_degree(), 3, "{}\n{}", criterion, selection);
+            assert_eq!(selection.scores().len(), 9);
+        }
+    }
+
+    #[test]
+    fn rss_decreases_and_table() {
+        let pts = cubic_data();
+        let selection = select_degree(|d| legendre_basis(d, 0.0, 5.0), &pts, None, 5, Criterion::Bic).unwrap();
+        assert!(selection.scores().windows(2).all(|w| w[1].rss <= w[0].rss * (1.0 + 1e-12)));
+
+        let table = selection.to_string();
+        assert_eq!(table.lines().count(), 7);
+        assert!(table.lines().next().unwrap().contains("BIC"));
+        assert!(table.lines().nth(4).unwrap().starts_with("    3*"));
+    }
+
+    #[test]
+    fn leave_one_out_matches_hat_formula() {
+        // For a straight line the LOO residual is r[i]/(1 - h[i]) with
+        // h[i] = 1/n + (x[i] - mean)^2/sxx.
+        let pts = [(0.0, 1.0), (1.0, 2.5), (2.0, 2.0), (3.0, 4.5), (5.0, 5.0)];
+        let fit = legendre_basis(1, 0.0, 5.0).fit(&pts, None).unwrap();
+        let n = pts.len() as f64;
+        let mean = pts.iter().map(|p| p.0).sum::<f64>() / n;
+        let sxx = pts.iter().map(|p| (p.0 - mean).powi(2)).sum::<f64>();
+        let expected = pts.iter()
+            .zip(fit.residuals())
+            .map(|(p, r)| (r / (1.0 - 1.0 / n - (p.0 - mean).powi(2) / sxx)).powi(2))
+            .sum::<f64>() / n;
+
+        let selection = select_degree(|d| legendre_basis(d, 0.0, 5.0), &pts, None, 1, Criterion::LeaveOneOut).unwrap();
+        assert!((selection.scores()[1].score - expected).abs() < 1e-12);
+    }
+
+    #[test]
+    fn too_high_degree() {
+        let pts = [(0.0, 1.0), (1.0, 2.5), (2.0, 2.0)];
+        assert_eq!(select_degree(|d| legendre_basis(d, 0.0, 2.0), &pts, None, 3, Criterion::Aic).unwrap_err(),
+                   FitError::TooFewPoints { needed: 4, found: 3 });
+    }
+
+    #[test]
+    fn invalid_folds() {
+        let pts = cubic_data();
+        for &folds in &[0, 1, 81] {
+            assert_eq!(select_degree(|d| legendre_basis(d, 0.0, 5.0), &pts, None, 3, Criterion::CrossValidation { folds }).unwrap_err(),
+                       FitError::InvalidFolds { folds, points: 80 });
+        }
+        assert!(select_degree(|d| legendre_basis(d, 0.0, 5.0), &pts, None, 3, Criterion::CrossValidation { folds: 80 }).is_ok());
+    }
+}
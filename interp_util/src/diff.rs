@@ -0,0 +1,242 @@
+use std::error::Error;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DiffError {
+    LengthMismatch { xs: usize, ys: usize },
+    TooFewPoints { needed: usize, found: usize },
+    // NaN or infinite coordinate in the input point with this index.
+    NotFinite { index: usize },
+    // xs[index] does not exceed xs[index - 1].
+    NotIncreasing { index: usize },
+}
+
+impl fmt::Display for DiffError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            DiffError::LengthMismatch { xs, ys } => write!(f, "{} abscissae but {} ordinates", xs, ys),
+            DiffError::TooFewPoints { needed, found } => write!(f, "stencil needs at least {} points, got {}", needed, found),
+            DiffError::NotFinite { index } => write!(f, "point #{} is not finite", index),
+            DiffError::NotIncreasing { index } => write!(f, "abscissa #{} is not increasing", index),
+        }
+    }
+}
+
+impl Error for DiffError {}
+
+// Order of the truncation error, O(h^p).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Accuracy {
+    Second,
+    Fourth,
+    Sixth,
+}
+
+impl Accuracy {
+    pub fn order(&self) -> usize {
+        match *self {
+            Accuracy::Second => 2,
+            Accuracy::Fourth => 4,
+            Accuracy::Sixth => 6,
+        }
+    }
+}
+
+// Fornberg's recursion for the weights of the finite-difference
+// approximations of the derivatives 0..max_order at x0 from the values at
+// xs, which may be spaced arbitrarily: f^(m)(x0) ~ sum(w[m][j]*f(xs[j])).
+// With n points the m-th derivative is exact for polynomials of degree n - 1.
+pub fn fornberg_weights(x0: f64, xs: &[f64], max_order: usize) -> Vec<Vec<f64>> {
+    let n = xs.len();
+    let mut w = vec![vec![0.0; n]; max_order + 1];
+    if n == 0 {
+        return w;
+    }
+
+    w[0][0] = 1.0;
+    let mut c1 = 1.0;
+    let mut c4 = xs[0] - x0;
+    for i in 1..n {
+        let mn = i.min(max_order);
+        let mut c2 = 1.0;
+        let c5 = c4;
+        c4 = xs[i] - x0;
+        for j in 0..i {
+            let c3 = xs[i] - xs[j];
+            c2 *= c3;
+            if j == i - 1 {
+                for k in (1..mn + 1).rev() {
+                    w[k][i] = c1 * (k as f64 * w[k - 1][i - 1] - c5 * w[k][i - 1]) / c2;
+                }
+                w[0][i] = -c1 * c5 * w[0][i - 1] / c2;
+            }
+            for k in (1..mn + 1).rev() {
+                w[k][j] = (c4 * w[k][j] - k as f64 * w[k - 1][j]) / c3;
+            }
+            w[0][j] *= c4 / c3;
+        }
+        c1 = c2;
+    }
+
+    w
+}
+
+// The order-th derivative at every abscissa, the same length as the input.
+// Each value uses order + p neighbouring points, as centred as the ends
+// allow, so the error is O(h^p) on any grid, the one-sided stencils at the
+// ends included. On a uniform grid a centred stencil with an even number of
+// points is cut to the symmetric one with a point less, which has the same
+// order: for the second derivative at Accuracy::Second the interior
+// stencil is the usual (y[i-1] - 2y[i] + y[i+1])/h^2.
+pub fn derivative(xs: &[f64], ys: &[f64], order: usize, accuracy: Accuracy) -> Result<Vec<f64>, DiffError> {
+    if xs.len() != ys.len() {
+        return Err(DiffError::LengthMismatch { xs: xs.len(), ys: ys.len() });
+    }
+    let n = xs.len();
+    let full = order + accuracy.order();
+    if n < full {
+        return Err(DiffError::TooFewPoints { needed: full, found: n });
+    }
+    if let Some(index) = (0..n).find(|&i| !xs[i].is_finite() || !ys[i].is_finite()) {
+        return Err(DiffError::NotFinite { index });
+    }
+    if let Some(index) = (1..n).find(|&i| xs[i] <= xs[i - 1]) {
+        return Err(DiffError::NotIncreasing { index });
+    }
+
+    let h = (xs[n - 1] - xs[0]) / (n - 1) as f64;
+    let uniform = (1..n).all(|i| (xs[i] - xs[i - 1] - h).abs() <= 1e-10 * h);
+    let central = if full % 2 == 1 || !uniform { full } else { full - 1 };
+
+    let res = (0..n)
+        .map(|i| {
+            let half = central / 2;
+            let (start, width) = if i >= half && i - half + central <= n {
+                (i - half, central)
+            } else {
+                (i.saturating_sub(full / 2).min(n - full), full)
+            };
+            let w = fornberg_weights(xs[i], &xs[start..start + width], order);
+            w[order].iter().zip(&ys[start..start + width]).map(|(w, y)| w * y).sum()
+        })
+        .collect();
+
+    Ok(res)
+}
+
+pub fn first_derivative(xs: &[f64], ys: &[f64], accuracy: Accuracy) -> Result<Vec<f64>, DiffError> {
+    derivative(xs, ys, 1, accuracy)
+}
+
+pub fn second_derivative(xs: &[f64], ys: &[f64], accuracy: Accuracy) -> Result<Vec<f64>, DiffError> {
+    derivative(xs, ys, 2, accuracy)
+}
+
+// derivative for samples ys[i] = f(x0 + i*h).
+pub fn uniform_derivative(ys: &[f64], h: f64, order: usize, accuracy: Accuracy) -> Result<Vec<f64>, DiffError> {
+    let xs = (0..ys.len()).map(|i| i as f64 * h).collect::<Vec<_>>();
+    derivative(&xs, ys, order, accuracy)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn max_error(values: &[f64], exact: &[f64]) -> f64 {
+        values.iter().zip(exact).fold(0.0f64, |acc, (v, e)| acc.max((v - e).abs()))
+    }
+
+    #[test]
+    fn classic_weights() {
+        let w = fornberg_weights(0.0, &[-1.0, 0.0, 1.0], 2);
+        assert_eq!(w[0], vec![0.0, 1.0, 0.0]);
+        assert_eq!(w[1], vec![-0.5, 0.0, 0.5]);
+        assert_eq!(w[2], vec![1.0, -2.0, 1.0]);
+
+        let w = fornberg_weights(0.0, &[-2.0, -1.0, 0.0, 1.0, 2.0], 2);
+        for (a, b) in w[2].iter().zip(&[-1.0 / 12.0, 4.0 / 3.0, -2.5, 4.0 / 3.0, -1.0 / 12.0]) {
+            assert!((a - b).abs() < 1e-14);
+        }
+        // One-sided: (-3y[0] + 4y[1] - y[2])/2h.
+        assert_eq!(fornberg_weights(0.0, &[0.0, 1.0, 2.0], 1)[1], vec![-1.5, 2.0, -0.5]);
+    }
+
+    #[test]
+    fn polynomials_are_exact() {
+        // order + p points differentiate polynomials of degree order + p - 1
+        // exactly, on a non-uniform grid and at the ends too.
+        let xs = (0..15).map(|i| i as f64 + 0.3 * (i as f64).sin()).collect::<Vec<_>>();
+        for &accuracy in &[Accuracy::Second, Accuracy::Fourth, Accuracy::Sixth] {
+            for order in 1..3 {
+                let degree = order + accuracy.order() - 1;
+                let ys = xs.iter().map(|&x| x.powi(degree as i32) - x).collect::<Vec<_>>();
+                let exact = xs.iter()
+                    .map(|&x| {
+                        let falling = (degree - order + 1..degree + 1).product::<usize>() as f64;
+                        falling * x.powi((degree - order) as i32) - if order == 1 { 1.0 } else { 0.0 }
+                    })
+                    .collect::<Vec<_>>();
+                let scale = exact.iter().fold(1.0f64, |acc, e| acc.max(e.abs()));
+                let d = derivative(&xs, &ys, order, accuracy).unwrap();
+                assert_eq!(d.len(), xs.len());
+                assert!(max_error(&d, &exact) < 1e-9 * scale, "{:?} order {}", accuracy, order);
+            }
+        }
+    }
+
+    #[test]
+    fn convergence_rates() {
+        // Halving the spacing divides the error by about 2^p, for a uniform
+        // grid and a stretched one alike.
+        let grid = |n: usize, stretched: bool| {
+            (0..n)
+                .map(|i| {
+                    let t = i as f64 / (n - 1) as f64;
+                    if stretched { t * t * 0.5 + t * 0.5 } else { t }
+                })
+                .collect::<Vec<_>>()
+        };
+        for &stretched in &[false, true] {
+            for &accuracy in &[Accuracy::Second, Accuracy::Fourth, Accuracy::Sixth] {
+                for order in 1..3 {
+                    let errors = [41, 81]
+                        .iter()
+                        .map(|&n| {
+                            let xs = grid(n, stretched);
+                            let ys = xs.iter().map(|&x| (3.0 * x).sin()).collect::<Vec<_>>();
+                            let exact = xs.iter()
+                                .map(|&x| if order == 1 { 3.0 * (3.0 * x).cos() } else { -9.0 * (3.0 * x).sin() })
+                                .collect::<Vec<_>>();
+                            max_error(&derivative(&xs, &ys, order, accuracy).unwrap(), &exact)
+                        })
+                        .collect::<Vec<_>>();
+                    let rate = (errors[0] / errors[1]).log2();
+                    assert!(rate > accuracy.order() as f64 - 0.5,
+                            "{:?} order {} stretched {}: rate {}", accuracy, order, stretched, rate);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn uniform_second_difference() {
+        let ys = [0.0, 1.0, 4.0, 9.0, 16.0, 30.0];
+        let d = uniform_derivative(&ys, 0.5, 2, Accuracy::Second).unwrap();
+        assert_eq!(d.len(), ys.len());
+        assert_eq!(d[1], (ys[0] - 2.0 * ys[1] + ys[2]) / 0.25);
+        assert_eq!(d[4], (ys[3] - 2.0 * ys[4] + ys[5]) / 0.25);
+        let xs = ::linspace(0.0, 2.5, 6);
+        assert_eq!(second_derivative(&xs, &ys, Accuracy::Second).unwrap(), d);
+    }
+
+    #[test]
+    fn invalid_input() {
+        let xs = [0.0, 1.0, 2.0, 3.0];
+        assert_eq!(first_derivative(&xs, &[1.0; 3], Accuracy::Second), Err(DiffError::LengthMismatch { xs: 4, ys: 3 }));
+        assert_eq!(second_derivative(&xs, &[1.0; 4], Accuracy::Fourth), Err(DiffError::TooFewPoints { needed: 6, found: 4 }));
+        assert_eq!(first_derivative(&[0.0, 1.0, 1.0, 2.0], &[1.0; 4], Accuracy::Second),
+                   Err(DiffError::NotIncreasing { index: 2 }));
+        assert_eq!(first_derivative(&xs, &[1.0, f64::NAN, 1.0, 1.0], Accuracy::Second),
+                   Err(DiffError::NotFinite { index: 1 }));
+    }
+}
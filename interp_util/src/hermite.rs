@@ -0,0 +1,139 @@
+use lagrange::NodeError;
+use newton::NewtonInterpolant;
+use spline::{check_points, from_slopes, CubicSpline, SplineError};
+
+// Hermite interpolating polynomial. Every point is (x, [f(x), f'(x), f''(x), ...]);
+// a node with k values enters the confluent divided-difference table k times,
+// and the repeated differences are f^(j)(x)/j!. The result is in Newton form,
+// so add_point can still extend it with plain values at new nodes.
+pub fn create_hermite_polynomial(pts: &[(f64, Vec<f64>)]) -> Result<NewtonInterpolant, NodeError> {
+    if pts.is_empty() {
+        return Err(NodeError::NoPoints);
+    }
+    for (index, &(x, ref values)) in pts.iter().enumerate() {
+        if values.is_empty() {
+            return Err(NodeError::NoValues { index });
+        }
+        if !x.is_finite() || values.iter().any(|v| !v.is_finite()) {
+            return Err(NodeError::NotFinite { index });
+        }
+        if pts[..index].iter().any(|&(prev, _)| prev == x) {
+            return Err(NodeError::DuplicateNode { x });
+        }
+    }
+
+    // z -- nodes with repetitions, q[i][j] = f[z[i-j], ..., z[i]]
+    let mut z = Vec::new();
+    let mut derivs = Vec::new();
+    for &(x, ref values) in pts {
+        for _ in 0..values.len() {
+            z.push(x);
+            derivs.push(values);
+        }
+    }
+    let m = z.len();
+    let mut q: Vec<Vec<f64>> = Vec::with_capacity(m);
+    for i in 0..m {
+        let mut row = Vec::with_capacity(i + 1);
+        row.push(derivs[i][0]);
+        let mut factorial = 1.0;
+        for j in 1..i + 1 {
+            factorial *= j as f64;
+            let dd = if z[i] == z[i - j] {
+                derivs[i][j] / factorial
+            } else {
+                (row[j - 1] - q[i - 1][j - 1]) / (z[i] - z[i - j])
+            };
+            row.push(dd);
+        }
+        q.push(row);
+    }
+
+    let coefs = (0..m).map(|i| q[i][i]).collect();
+    let last_row = q.pop().unwrap();
+
+    Ok(NewtonInterpolant::from_table(z, coefs, last_row))
+}
+
+// Piecewise cubic Hermite spline through the (x, y, y') triples: every
+// section matches the values and the slopes at its ends, so the result is C1.
+pub fn create_cubic_hermite_spline(pts: &[(f64, f64, f64)]) -> Result<CubicSpline, SplineError> {
+    if let Some(index) = pts.iter().position(|&(_, _, d)| !d.is_finite()) {
+        return Err(SplineError::NotFinite { index });
+    }
+    check_points(&pts.iter().map(|&(x, y, _)| (x, y)).collect::<Vec<_>>(), 2)?;
+    let mut sorted = pts.to_vec();
+    sorted.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+
+    let t = sorted.iter().map(|&(x, _, _)| x).collect::<Vec<_>>();
+    let y = sorted.iter().map(|&(_, y, _)| y).collect::<Vec<_>>();
+    let m = sorted.iter().map(|&(_, _, d)| d).collect::<Vec<_>>();
+
+    Ok(from_slopes(t, &y, &m))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hermite_polynomial() {
+        // Degree 5 polynomial from f, f', f'' at two nodes.
+        let f = |x: f64| ((((x - 2.0) * x + 0.5) * x + 1.0) * x - 3.0) * x + 1.0;
+        let df = |x: f64| (((5.0 * x - 8.0) * x + 1.5) * x + 2.0) * x - 3.0;
+        let d2f = |x: f64| ((20.0 * x - 24.0) * x + 3.0) * x + 2.0;
+        let pts = [(-1.0, vec![f(-1.0), df(-1.0), d2f(-1.0)]), (1.5, vec![f(1.5), df(1.5), d2f(1.5)])];
+        let hermite = create_hermite_polynomial(&pts).unwrap();
+        assert_eq!(hermite.nodes(), &[-1.0, -1.0, -1.0, 1.5, 1.5, 1.5]);
+        for &x in &[-1.0, -0.3, 0.0, 0.8, 1.5, 2.0] {
+            assert!((hermite.calc(x) - f(x)).abs() < 1e-11);
+        }
+    }
+
+    #[test]
+    fn hermite_polynomial_mixed_data() {
+        // f(x) = x^4 - x: value and slope at 0, value at 1, value and slope at 2.
+        let f = |x: f64| x.powi(4) - x;
+        let df = |x: f64| 4.0 * x.powi(3) - 1.0;
+        let pts = [(0.0, vec![f(0.0), df(0.0)]), (1.0, vec![f(1.0)]), (2.0, vec![f(2.0), df(2.0)])];
+        let mut hermite = create_hermite_polynomial(&pts).unwrap();
+        for &x in &[0.25, 0.5, 1.5, 1.9] {
+            assert!((hermite.calc(x) - f(x)).abs() < 1e-12);
+        }
+
+        // Adding a plain value keeps the polynomial exact.
+        hermite.add_point(3.0, f(3.0)).unwrap();
+        assert!(hermite.coefficients()[5].abs() < 1e-12);
+    }
+
+    #[test]
+    fn hermite_polynomial_errors() {
+        assert_eq!(create_hermite_polynomial(&[(0.0, vec![1.0]), (1.0, vec![])]).unwrap_err(),
+                   NodeError::NoValues { index: 1 });
+        assert_eq!(create_hermite_polynomial(&[(0.0, vec![1.0]), (0.0, vec![1.0, 2.0])]).unwrap_err(),
+                   NodeError::DuplicateNode { x: 0.0 });
+    }
+
+    #[test]
+    fn cubic_hermite_spline() {
+        let pts = [0.0f64, 0.4, 1.0, 1.7, 2.5, 3.0]
+            .iter()
+            .map(|&x| (x, x.sin(), x.cos()))
+            .collect::<Vec<_>>();
+        let spline = create_cubic_hermite_spline(&pts).unwrap();
+        for &(x, y, d) in &pts {
+            assert!((spline.calc(x) - y).abs() < 1e-12);
+            assert!((spline.calc_der(x) - d).abs() < 1e-12);
+        }
+        for &x in &[0.2, 0.7, 1.3, 2.1, 2.8] {
+            assert!((spline.calc(x) - x.sin()).abs() < 1e-3);
+        }
+
+        let cubic = |x: f64| x * x * x - x;
+        let pts = [(2.0, cubic(2.0), 11.0), (-1.0, cubic(-1.0), 2.0), (0.5, cubic(0.5), -0.25)];
+        let spline = create_cubic_hermite_spline(&pts).unwrap();
+        for &x in &[-0.5, 0.0, 1.0, 1.5] {
+            assert!((spline.calc(x) - cubic(x)).abs() < 1e-12);
+        }
+    }
+}
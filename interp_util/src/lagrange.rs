@@ -0,0 +1,263 @@
+use std::error::Error;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NodeError {
+    NoPoints,
+    // NaN or infinite coordinate in the input point with this index.
+    NotFinite { index: usize },
+    DuplicateNode { x: f64 },
+    // The point with this index carries no value.
+    NoValues { index: usize },
+}
+
+impl fmt::Display for NodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            NodeError::NoPoints => write!(f, "no interpolation nodes"),
+            NodeError::NotFinite { index } => write!(f, "point #{} is not finite", index),
+            NodeError::DuplicateNode { x } => write!(f, "duplicate node {}", x),
+            NodeError::NoValues { index } => write!(f, "point #{} has no values", index),
+        }
+    }
+}
+
+impl Error for NodeError {}
+
+// Interpolating polynomial in barycentric form. The weights
+// w[j] = 1 / prod(x[j] - x[k], k != j) are computed once, after that
+// evaluation is O(n) with the second (true) barycentric formula
+// p(x) = sum(w[j]*y[j]/(x - x[j])) / sum(w[j]/(x - x[j])).
+#[derive(Debug, Clone)]
+pub struct LagrangeInterpolant {
+    nodes: Vec<f64>,
+    values: Vec<f64>,
+    weights: Vec<f64>,
+}
+
+impl LagrangeInterpolant {
+    pub fn new(pts: &[(f64, f64)]) -> Result<LagrangeInterpolant, NodeError> {
+        if pts.is_empty() {
+            return Err(NodeError::NoPoints);
+        }
+
+        let mut res = LagrangeInterpolant {
+            nodes: Vec::with_capacity(pts.len()),
+            values: Vec::with_capacity(pts.len()),
+            weights: Vec::with_capacity(pts.len()),
+        };
+        for &(x, y) in pts {
+            res.add_node(x, y)?;
+        }
+
+        Ok(res)
+    }
+
+    // Adds a node in O(n): the old weights are divided by (x[j] - x) and the
+    // new weight is computed from scratch.
+    pub fn add_node(&mut self, x: f64, y: f64) -> Result<(), NodeError> {
+        if !x.is_finite() || !y.is_finite() {
+            return Err(NodeError::NotFinite { index: self.nodes.len() });
+        }
+        if self.nodes.contains(&x) {
+            return Err(NodeError::DuplicateNode { x });
+        }
+
+        let mut w = 1.0;
+        for (wj, &xj) in self.weights.iter_mut().zip(self.nodes.iter()) {
+            *wj /= xj - x;
+            w /= x - xj;
+        }
+        self.nodes.push(x);
+        self.values.push(y);
+        self.weights.push(w);
+
+        Ok(())
+    }
+
+    pub fn nodes(&self) -> &[f64] {
+        &self.nodes
+    }
+
+    pub fn values(&self) -> &[f64] {
+        &self.values
+    }
+
+    pub fn weights(&self) -> &[f64] {
+        &self.weights
+    }
+
+    pub fn calc(&self, x: f64) -> f64 {
+        let mut num = 0.0;
+        let mut den = 0.0;
+        for ((&xj, &yj), &wj) in self.nodes.iter().zip(self.values.iter()).zip(self.weights.iter()) {
+            if x == xj {
+                return yj;
+            }
+            let t = wj / (x - xj);
+            num += t * yj;
+            den += t;
+        }
+
+        num / den
+    }
+
+    pub fn calc_der(&self, x: f64) -> f64 {
+        self.calc_nth_der(1, x)
+    }
+
+    pub fn calc_der2(&self, x: f64) -> f64 {
+        self.calc_nth_der(2, x)
+    }
+
+    // O(order * n^2); use derivative() to evaluate one derivative at many points.
+    pub fn calc_nth_der(&self, order: usize, x: f64) -> f64 {
+        self.derivative(order).calc(x)
+    }
+
+    // The derivative of the given order as an interpolant on the same nodes:
+    // p^(k) has degree below n, so it is reproduced exactly from its values at
+    // the nodes, which are D^(k)*y.
+    pub fn derivative(&self, order: usize) -> LagrangeInterpolant {
+        let values = if order == 0 {
+            self.values.clone()
+        } else if order >= self.nodes.len() {
+            vec![0.0; self.nodes.len()]
+        } else {
+            let d = self.differentiation_matrix(order);
+            d.iter()
+                .map(|row| row.iter().zip(self.values.iter()).map(|(dij, yj)| dij * yj).sum())
+                .collect()
+        };
+
+        LagrangeInterpolant {
+            nodes: self.nodes.clone(),
+            values,
+            weights: self.weights.clone(),
+        }
+    }
+
+    // Matrix D^(k) mapping the values at the nodes to the values of the k-th
+    // derivative there, built with the recurrence of Schneider and Werner:
+    // D^(k)[i][j] = k/(x[i] - x[j]) * (w[j]/w[i]*D^(k-1)[i][i] - D^(k-1)[i][j]),
+    // with the diagonal chosen so that every row sums to zero.
+    pub fn differentiation_matrix(&self, order: usize) -> Vec<Vec<f64>> {
+        let n = self.nodes.len();
+        let (x, w) = (&self.nodes, &self.weights);
+
+        let mut d = (0..n)
+            .map(|i| {
+                let mut row = vec![0.0; n];
+                row[i] = 1.0;
+                row
+            })
+            .collect::<Vec<_>>();
+        for k in 1..order + 1 {
+            for (i, row) in d.iter_mut().enumerate() {
+                let prev_diag = row[i];
+                let mut diag = 0.0;
+                for j in 0..n {
+                    if j != i {
+                        row[j] = k as f64 / (x[i] - x[j]) * (w[j] / w[i] * prev_diag - row[j]);
+                        diag -= row[j];
+                    }
+                }
+                row[i] = diag;
+            }
+        }
+
+        d
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn poly(x: f64) -> f64 {
+        ((0.5 * x - 1.0) * x + 2.0) * x * x - 3.0
+    }
+
+    #[test]
+    fn reproduces_polynomial() {
+        let pts = [-2.0, -0.7, 0.1, 1.4, 2.5].iter().map(|&x| (x, poly(x))).collect::<Vec<_>>();
+        let lagrange = LagrangeInterpolant::new(&pts).unwrap();
+        for &(x, y) in &pts {
+            assert_eq!(lagrange.calc(x), y);
+        }
+        for &x in &[-3.0, -1.1, 0.0, 0.5, 2.0, 4.0] {
+            assert!((lagrange.calc(x) - poly(x)).abs() < 1e-10 * poly(x).abs().max(1.0));
+        }
+    }
+
+    #[test]
+    fn derivatives() {
+        let dpoly = |x: f64| ((2.0 * x - 3.0) * x + 4.0) * x;
+        let d2poly = |x: f64| (6.0 * x - 6.0) * x + 4.0;
+        let d3poly = |x: f64| 12.0 * x - 6.0;
+
+        let pts = [-2.0, -0.7, 0.1, 1.4, 2.5, 3.0].iter().map(|&x| (x, poly(x))).collect::<Vec<_>>();
+        let lagrange = LagrangeInterpolant::new(&pts).unwrap();
+        for &x in &[-2.0, -1.1, 0.0, 0.1, 1.4, 2.8, 3.0] {
+            assert!((lagrange.calc_der(x) - dpoly(x)).abs() < 1e-10, "p'({}) = {}", x, lagrange.calc_der(x));
+            assert!((lagrange.calc_der2(x) - d2poly(x)).abs() < 1e-9);
+            assert!((lagrange.calc_nth_der(3, x) - d3poly(x)).abs() < 1e-8);
+            assert!((lagrange.calc_nth_der(4, x) - 12.0).abs() < 1e-8);
+            assert!(lagrange.calc_nth_der(5, x).abs() < 1e-8);
+            assert_eq!(lagrange.calc_nth_der(6, x), 0.0);
+        }
+    }
+
+    #[test]
+    fn derivatives_of_sine() {
+        let pts = ::linspace(0.0, 1.5, 12).iter().map(|&x| (x, x.sin())).collect::<Vec<_>>();
+        let lagrange = LagrangeInterpolant::new(&pts).unwrap();
+        let der = lagrange.derivative(1);
+        let der3 = lagrange.derivative(3);
+        for &x in &[0.0, 0.3, 0.75, 1.5] {
+            assert!((der.calc(x) - x.cos()).abs() < 1e-8);
+            assert!((der3.calc(x) + x.cos()).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn add_node_matches_rebuild() {
+        let pts = [0.0f64, 0.3, 1.1, 2.0, 2.2].iter().map(|&x| (x, (2.0 * x).cos())).collect::<Vec<_>>();
+        let full = LagrangeInterpolant::new(&pts).unwrap();
+        let mut incremental = LagrangeInterpolant::new(&pts[..2]).unwrap();
+        for &(x, y) in &pts[2..] {
+            incremental.add_node(x, y).unwrap();
+        }
+        for (a, b) in full.weights().iter().zip(incremental.weights().iter()) {
+            assert!((a - b).abs() < 1e-12 * a.abs());
+        }
+        for &x in &[0.2, 0.9, 1.7] {
+            assert!((full.calc(x) - incremental.calc(x)).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn chebyshev_nodes_runge() {
+        use std::f64::consts::PI;
+
+        let n = 40;
+        let runge = |x: f64| 1.0 / (1.0 + 25.0 * x * x);
+        let pts = (0..n)
+            .map(|k| (PI * (2 * k + 1) as f64 / (2 * n) as f64).cos())
+            .map(|x| (x, runge(x)))
+            .collect::<Vec<_>>();
+        let lagrange = LagrangeInterpolant::new(&pts).unwrap();
+        for &x in &::linspace(-1.0, 1.0, 101) {
+            assert!((lagrange.calc(x) - runge(x)).abs() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn invalid_nodes() {
+        assert_eq!(LagrangeInterpolant::new(&[]).unwrap_err(), NodeError::NoPoints);
+        assert_eq!(LagrangeInterpolant::new(&[(0.0, 1.0), (1.0, 2.0), (0.0, 3.0)]).unwrap_err(),
+                   NodeError::DuplicateNode { x: 0.0 });
+        assert_eq!(LagrangeInterpolant::new(&[(0.0, 1.0), (f64::NAN, 2.0)]).unwrap_err(),
+                   NodeError::NotFinite { index: 1 });
+    }
+}
@@ -1,9 +1,22 @@
-#[cfg(test)]
-mod tests {
-    #[test]
-    fn it_works() {
-    }
-}
+extern crate rand;
+
+pub mod bspline;
+pub mod degree;
+pub mod diff;
+pub mod hermite;
+pub mod lagrange;
+pub mod linalg;
+pub mod lsq;
+pub mod median_line;
+pub mod newton;
+pub mod orthopoly;
+pub mod outlier;
+pub mod ransac;
+pub mod robust;
+pub mod smoothing;
+pub mod spline;
+pub mod stats;
+pub mod synthetic;
 
 pub fn linspace(min: f64, max: f64, num: usize) -> Vec<f64> {
     let dt = (max - min) / ((num - 1) as f64);
@@ -15,3 +28,29 @@ pub fn linspace(min: f64, max: f64, num: usize) -> Vec<f64> {
     pts
 }
 
+#[cfg(test)]
+mod tests {
+    // Deterministic zero-mean noise in [-0.5, 0.5) for the tests of the
+    // fitting modules.
+    pub fn noise(i: usize) -> f64 {
+        ((i * 7919 + 13) % 101) as f64 / 101.0 - 0.5
+    }
+
+    // y = 1.5x + 1 with small noise and three gross outliers at the returned
+    // indices, for the robust line estimators.
+    pub fn contaminated_line() -> (Vec<(f64, f64)>, Vec<usize>) {
+        let outliers = vec![10, 47, 80];
+        let pts = (0..100)
+            .map(|i| {
+                let x = i as f64;
+                let y = 1.5 * x + 1.0 + 2.0 * noise(i);
+                (x, if outliers.contains(&i) { y + 80.0 } else { y })
+            })
+            .collect();
+        (pts, outliers)
+    }
+
+    #[test]
+    fn it_works() {
+    }
+}
@@ -0,0 +1,804 @@
+use std::error::Error;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LinalgError {
+    // The system has no equations.
+    Empty,
+    // One of the inputs has the wrong length for the system size.
+    DimensionMismatch { what: &'static str, expected: usize, found: usize },
+    // Zero, non-finite or round-off sized pivot in the given row.
+    Singular { row: usize },
+    // Pivot much smaller than the rest of its row; ratio = |pivot| / max|row|.
+    IllConditioned { row: usize, ratio: f64 },
+}
+
+impl fmt::Display for LinalgError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            LinalgError::Empty => write!(f, "empty system"),
+            LinalgError::DimensionMismatch { what, expected, found } => {
+                write!(f, "{} has length {}, expected {}", what, found, expected)
+            }
+            LinalgError::Singular { row } => write!(f, "singular pivot in row {}", row),
+            LinalgError::IllConditioned { row, ratio } => {
+                write!(f, "ill-conditioned pivot in row {} (relative size {:e})", row, ratio)
+            }
+        }
+    }
+}
+
+impl Error for LinalgError {}
+
+// How solve_tridiagonal_with treats the pivots.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Pivoting {
+    // Thomas algorithm; a bad pivot is an error.
+    Never,
+    // Thomas algorithm, repeated with partial pivoting if a pivot is bad.
+    Fallback,
+    // Gaussian elimination with partial pivoting, as LAPACK gtsv.
+    Always,
+}
+
+// Pivots below this fraction of their row are reported as ill-conditioned.
+fn ill_conditioned_ratio() -> f64 {
+    f64::EPSILON.sqrt()
+}
+
+fn check_pivot(row: usize, pivot: f64, scale: f64) -> Result<(), LinalgError> {
+    if !pivot.is_finite() || pivot.abs() <= f64::EPSILON * scale {
+        return Err(LinalgError::Singular { row });
+    }
+    let ratio = pivot.abs() / scale;
+    if ratio < ill_conditioned_ratio() {
+        return Err(LinalgError::IllConditioned { row, ratio });
+    }
+
+    Ok(())
+}
+
+fn check_tridiagonal(a: &[f64], b: &[f64], c: &[f64], d: &[f64]) -> Result<(), LinalgError> {
+    let n = b.len();
+    if n == 0 {
+        return Err(LinalgError::Empty);
+    }
+    for &(what, len, expected) in &[("lower diagonal", a.len(), n - 1),
+                                    ("upper diagonal", c.len(), n - 1),
+                                    ("right-hand side", d.len(), n)] {
+        if len != expected {
+            return Err(LinalgError::DimensionMismatch { what, expected, found: len });
+        }
+    }
+
+    Ok(())
+}
+
+// Largest absolute entry in each row of the tridiagonal matrix.
+fn row_scales(a: &[f64], b: &[f64], c: &[f64]) -> Vec<f64> {
+    (0..b.len())
+        .map(|i| {
+            let lower = if i > 0 { a[i - 1].abs() } else { 0.0 };
+            let upper = if i < c.len() { c[i].abs() } else { 0.0 };
+            b[i].abs().max(lower).max(upper)
+        })
+        .collect()
+}
+
+// a -- lower diagonal
+// b -- middle
+// c -- upper
+// d -- result column
+pub fn solve_tridiagonal(a: &[f64], b: &[f64], c: &[f64], d: &[f64]) -> Result<Vec<f64>, LinalgError> {
+    solve_tridiagonal_with(a, b, c, d, Pivoting::Never)
+}
+
+pub fn solve_tridiagonal_with(a: &[f64], b: &[f64], c: &[f64], d: &[f64], pivoting: Pivoting) -> Result<Vec<f64>, LinalgError> {
+    check_tridiagonal(a, b, c, d)?;
+    let scales = row_scales(a, b, c);
+
+    match pivoting {
+        Pivoting::Never => thomas(a, b, c, d, &scales),
+        Pivoting::Always => partial_pivoting(a, b, c, d, &scales),
+        Pivoting::Fallback => {
+            match thomas(a, b, c, d, &scales) {
+                Err(LinalgError::Singular { .. }) |
+                Err(LinalgError::IllConditioned { .. }) => partial_pivoting(a, b, c, d, &scales),
+                res => res,
+            }
+        }
+    }
+}
+
+fn thomas(a: &[f64], b: &[f64], c: &[f64], d: &[f64], scales: &[f64]) -> Result<Vec<f64>, LinalgError> {
+    let n = b.len();
+    let mut cm = Vec::with_capacity(n - 1);
+    let mut dm = Vec::with_capacity(n);
+
+    check_pivot(0, b[0], scales[0])?;
+    if n > 1 {
+        cm.push(c[0] / b[0]);
+    }
+    dm.push(d[0] / b[0]);
+    for i in 1..n {
+        let pivot = b[i] - a[i - 1] * cm[i - 1];
+        check_pivot(i, pivot, scales[i])?;
+        if i < n - 1 {
+            cm.push(c[i] / pivot);
+        }
+        dm.push((d[i] - a[i - 1] * dm[i - 1]) / pivot);
+    }
+
+    let mut res = vec![0.0; n];
+    res[n - 1] = dm[n - 1];
+    for i in (0..n - 1).rev() {
+        res[i] = dm[i] - cm[i] * res[i + 1];
+    }
+
+    Ok(res)
+}
+
+// Row interchanges between neighbouring rows give an upper triangular factor
+// with a second superdiagonal, kept in du2.
+fn partial_pivoting(a: &[f64], b: &[f64], c: &[f64], d: &[f64], scales: &[f64]) -> Result<Vec<f64>, LinalgError> {
+    let n = b.len();
+    let mut diag = b.to_vec();
+    let mut du = c.to_vec();
+    let mut du2 = vec![0.0; n.saturating_sub(2)];
+    let mut rhs = d.to_vec();
+
+    for i in 0..n - 1 {
+        if diag[i].abs() >= a[i].abs() {
+            check_pivot(i, diag[i], scales[i])?;
+            let fact = a[i] / diag[i];
+            diag[i + 1] -= fact * du[i];
+            rhs[i + 1] -= fact * rhs[i];
+        } else {
+            check_pivot(i, a[i], scales[i + 1])?;
+            let fact = diag[i] / a[i];
+            diag[i] = a[i];
+            let tmp = diag[i + 1];
+            diag[i + 1] = du[i] - fact * tmp;
+            if i < n - 2 {
+                du2[i] = du[i + 1];
+                du[i + 1] = -fact * du2[i];
+            }
+            du[i] = tmp;
+            let tmp = rhs[i];
+            rhs[i] = rhs[i + 1];
+            rhs[i + 1] = tmp - fact * rhs[i + 1];
+        }
+    }
+    check_pivot(n - 1, diag[n - 1], scales[n - 1])?;
+
+    let mut res = vec![0.0; n];
+    res[n - 1] = rhs[n - 1] / diag[n - 1];
+    if n > 1 {
+        res[n - 2] = (rhs[n - 2] - du[n - 2] * res[n - 1]) / diag[n - 2];
+    }
+    for i in (0..n.saturating_sub(2)).rev() {
+        res[i] = (rhs[i] - du[i] * res[i + 1] - du2[i] * res[i + 2]) / diag[i];
+    }
+
+    Ok(res)
+}
+
+// Tridiagonal system with the corner entries alpha (last row, first column)
+// and beta (first row, last column), solved with the Sherman-Morrison formula.
+// Needs at least three equations.
+pub fn solve_cyclic_tridiagonal(a: &[f64], b: &[f64], c: &[f64], d: &[f64], alpha: f64, beta: f64) -> Result<Vec<f64>, LinalgError> {
+    check_tridiagonal(a, b, c, d)?;
+    let n = b.len();
+    if n < 3 {
+        return Err(LinalgError::DimensionMismatch { what: "cyclic system", expected: 3, found: n });
+    }
+    let gamma = -b[0];
+    if gamma == 0.0 {
+        return Err(LinalgError::Singular { row: 0 });
+    }
+
+    let mut bb = b.to_vec();
+    bb[0] = b[0] - gamma;
+    bb[n - 1] = b[n - 1] - alpha * beta / gamma;
+    let x = solve_tridiagonal_with(a, &bb, c, d, Pivoting::Fallback)?;
+
+    let mut u = vec![0.0; n];
+    u[0] = gamma;
+    u[n - 1] = alpha;
+    let z = solve_tridiagonal_with(a, &bb, c, &u, Pivoting::Fallback)?;
+
+    let denom = 1.0 + z[0] + beta * z[n - 1] / gamma;
+    if denom.is_nan() || denom.abs() <= f64::EPSILON {
+        return Err(LinalgError::Singular { row: 0 });
+    }
+    let fact = (x[0] + beta * x[n - 1] / gamma) / denom;
+
+    Ok(x.iter().zip(z.iter()).map(|(x, z)| x - fact * z).collect())
+}
+
+// Symmetric band matrix stored by diagonals: bands[k][i] = A[i][i + k]
+// for k = 0..bandwidth.
+#[derive(Debug, Clone)]
+pub struct SymmetricBanded {
+    bands: Vec<Vec<f64>>,
+}
+
+impl SymmetricBanded {
+    pub fn zeros(n: usize, bandwidth: usize) -> SymmetricBanded {
+        SymmetricBanded { bands: (0..bandwidth + 1).map(|k| vec![0.0; n.saturating_sub(k)]).collect() }
+    }
+
+    pub fn size(&self) -> usize {
+        self.bands[0].len()
+    }
+
+    pub fn bandwidth(&self) -> usize {
+        self.bands.len() - 1
+    }
+
+    // A[i][j]; zero outside of the band.
+    pub fn get(&self, i: usize, j: usize) -> f64 {
+        let (i, k) = if i <= j { (i, j - i) } else { (j, i - j) };
+        if k < self.bands.len() { self.bands[k][i] } else { 0.0 }
+    }
+
+    // Adds v to A[i][j] (and to A[j][i]); panics outside of the band.
+    pub fn add(&mut self, i: usize, j: usize, v: f64) {
+        let (i, k) = if i <= j { (i, j - i) } else { (j, i - j) };
+        self.bands[k][i] += v;
+    }
+
+    // A = L*D*L^T with unit lower triangular L of the same bandwidth. Fails
+    // if the matrix is not (numerically) positive definite.
+    pub fn ldl(&self) -> Result<BandedLdl, LinalgError> {
+        let n = self.size();
+        if n == 0 {
+            return Err(LinalgError::Empty);
+        }
+        let p = self.bandwidth();
+        let scale = self.bands.iter().flat_map(|band| band.iter()).fold(0.0f64, |acc, v| acc.max(v.abs()));
+
+        // l[k][j] = L[j + k][j]
+        let mut l = (0..p + 1).map(|k| vec![0.0; n.saturating_sub(k)]).collect::<Vec<_>>();
+        let mut d = vec![0.0; n];
+        for j in 0..n {
+            let first = j.saturating_sub(p);
+            let mut dj = self.bands[0][j];
+            for k in first..j {
+                dj -= l[j - k][k] * l[j - k][k] * d[k];
+            }
+            if !dj.is_finite() || dj <= f64::EPSILON * scale {
+                return Err(LinalgError::Singular { row: j });
+            }
+            d[j] = dj;
+            l[0][j] = 1.0;
+
+            for i in j + 1..(j + p + 1).min(n) {
+                let mut v = self.bands[i - j][j];
+                for k in i.saturating_sub(p)..j {
+                    v -= l[i - k][k] * l[j - k][k] * d[k];
+                }
+                l[i - j][j] = v / dj;
+            }
+        }
+
+        Ok(BandedLdl { l, d })
+    }
+}
+
+// L*D*L^T factorization of a SymmetricBanded matrix.
+#[derive(Debug, Clone)]
+pub struct BandedLdl {
+    l: Vec<Vec<f64>>,
+    d: Vec<f64>,
+}
+
+impl BandedLdl {
+    pub fn solve(&self, rhs: &[f64]) -> Result<Vec<f64>, LinalgError> {
+        let n = self.d.len();
+        if rhs.len() != n {
+            return Err(LinalgError::DimensionMismatch { what: "right-hand side", expected: n, found: rhs.len() });
+        }
+        let p = self.l.len() - 1;
+
+        let mut x = rhs.to_vec();
+        for i in 0..n {
+            for k in i.saturating_sub(p)..i {
+                x[i] -= self.l[i - k][k] * x[k];
+            }
+        }
+        for (xi, di) in x.iter_mut().zip(self.d.iter()) {
+            *xi /= di;
+        }
+        for i in (0..n).rev() {
+            for k in i + 1..(i + p + 1).min(n) {
+                x[i] -= self.l[k - i][i] * x[k];
+            }
+        }
+
+        Ok(x)
+    }
+
+    // The entries of A^-1 inside the band of A, computed backwards from the
+    // factorization in O(n * bandwidth^2) (Hutchinson and de Hoog).
+    pub fn inverse_band(&self) -> SymmetricBanded {
+        let n = self.d.len();
+        let p = self.l.len() - 1;
+        let mut inv = SymmetricBanded::zeros(n, p);
+
+        for i in (0..n).rev() {
+            let last = (i + p).min(n - 1);
+            for j in (i + 1..last + 1).rev() {
+                let mut v = 0.0;
+                for k in 1..last - i + 1 {
+                    v -= self.l[k][i] * inv.get(i + k, j);
+                }
+                inv.bands[j - i][i] = v;
+            }
+            let mut v = 1.0 / self.d[i];
+            for k in 1..last - i + 1 {
+                v -= self.l[k][i] * inv.get(i + k, i);
+            }
+            inv.bands[0][i] = v;
+        }
+
+        inv
+    }
+}
+
+// Columns of a dense matrix given by its rows; all rows must have the same
+// length.
+fn columns(a: &[Vec<f64>]) -> Result<Vec<Vec<f64>>, LinalgError> {
+    if a.is_empty() || a[0].is_empty() {
+        return Err(LinalgError::Empty);
+    }
+    let n = a[0].len();
+    if let Some(row) = a.iter().find(|row| row.len() != n) {
+        return Err(LinalgError::DimensionMismatch { what: "matrix row", expected: n, found: row.len() });
+    }
+
+    Ok((0..n).map(|j| a.iter().map(|row| row[j]).collect()).collect())
+}
+
+fn dot(a: &[f64], b: &[f64]) -> f64 {
+    a.iter().zip(b.iter()).map(|(x, y)| x * y).sum()
+}
+
+// Householder QR of an m x n matrix with m >= n: A = Q*R. Q is kept as the
+// Householder vectors and applied on demand.
+#[derive(Debug, Clone)]
+pub struct Qr {
+    // Column j holds R[..j][j] above the diagonal and the j-th Householder
+    // vector from the diagonal down.
+    cols: Vec<Vec<f64>>,
+    r_diag: Vec<f64>,
+    betas: Vec<f64>,
+}
+
+// a is given by its rows.
+pub fn qr_decompose(a: &[Vec<f64>]) -> Result<Qr, LinalgError> {
+    let mut cols = columns(a)?;
+    let (m, n) = (a.len(), cols.len());
+    if m < n {
+        return Err(LinalgError::DimensionMismatch { what: "matrix column", expected: n, found: m });
+    }
+
+    let mut r_diag = vec![0.0; n];
+    let mut betas = vec![0.0; n];
+    for k in 0..n {
+        let norm = dot(&cols[k][k..], &cols[k][k..]).sqrt();
+        if norm == 0.0 {
+            continue;
+        }
+        let alpha = if cols[k][k] > 0.0 { -norm } else { norm };
+        cols[k][k] -= alpha;
+        let beta = 2.0 / dot(&cols[k][k..], &cols[k][k..]);
+        let (left, right) = cols.split_at_mut(k + 1);
+        let v = &left[k][k..];
+        for col in right.iter_mut() {
+            let s = beta * dot(v, &col[k..]);
+            for (ci, vi) in col[k..].iter_mut().zip(v.iter()) {
+                *ci -= s * vi;
+            }
+        }
+        r_diag[k] = alpha;
+        betas[k] = beta;
+    }
+
+    Ok(Qr { cols, r_diag, betas })
+}
+
+impl Qr {
+    // Q^T*b.
+    pub fn apply_qt(&self, b: &[f64]) -> Result<Vec<f64>, LinalgError> {
+        let m = self.cols[0].len();
+        if b.len() != m {
+            return Err(LinalgError::DimensionMismatch { what: "right-hand side", expected: m, found: b.len() });
+        }
+
+        let mut res = b.to_vec();
+        for (k, col) in self.cols.iter().enumerate() {
+            let v = &col[k..];
+            let s = self.betas[k] * dot(v, &res[k..]);
+            for (ri, vi) in res[k..].iter_mut().zip(v.iter()) {
+                *ri -= s * vi;
+            }
+        }
+
+        Ok(res)
+    }
+
+    // The n x n upper triangular factor, by rows.
+    pub fn r(&self) -> Vec<Vec<f64>> {
+        let n = self.cols.len();
+        (0..n)
+            .map(|i| (0..n).map(|j| if j < i { 0.0 } else if j == i { self.r_diag[i] } else { self.cols[j][i] }).collect())
+            .collect()
+    }
+
+    // x minimizing |A*x - b|; fails on a zero or round-off sized diagonal
+    // entry of R.
+    pub fn solve_least_squares(&self, b: &[f64]) -> Result<Vec<f64>, LinalgError> {
+        let n = self.cols.len();
+        let qtb = self.apply_qt(b)?;
+        let scale = self.r_diag.iter().fold(0.0f64, |acc, r| acc.max(r.abs()));
+
+        let mut x = qtb[..n].to_vec();
+        for i in (0..n).rev() {
+            if self.r_diag[i].abs() <= f64::EPSILON * scale {
+                return Err(LinalgError::Singular { row: i });
+            }
+            for j in i + 1..n {
+                x[i] -= self.cols[j][i] * x[j];
+            }
+            x[i] /= self.r_diag[i];
+        }
+
+        Ok(x)
+    }
+}
+
+// Singular value decomposition A = U*diag(s)*V^T of an m x n matrix, with the
+// singular values in decreasing order.
+#[derive(Debug, Clone)]
+pub struct Svd {
+    // Left and right singular vectors, one Vec per vector.
+    u: Vec<Vec<f64>>,
+    s: Vec<f64>,
+    v: Vec<Vec<f64>>,
+}
+
+// One-sided Jacobi (Hestenes): plane rotations from the right make the
+// columns of A*V mutually orthogonal; their norms are the singular values.
+// a is given by its rows.
+pub fn svd(a: &[Vec<f64>]) -> Result<Svd, LinalgError> {
+    let mut cols = columns(a)?;
+    let n = cols.len();
+    let mut v = (0..n)
+        .map(|j| {
+            let mut col = vec![0.0; n];
+            col[j] = 1.0;
+            col
+        })
+        .collect::<Vec<_>>();
+
+    for _ in 0..100 {
+        let mut rotated = false;
+        for p in 0..n {
+            for q in p + 1..n {
+                let alpha = dot(&cols[p], &cols[p]);
+                let beta = dot(&cols[q], &cols[q]);
+                let gamma = dot(&cols[p], &cols[q]);
+                if gamma == 0.0 || gamma.abs() <= f64::EPSILON * (alpha * beta).sqrt() {
+                    continue;
+                }
+                rotated = true;
+
+                let zeta = (beta - alpha) / (2.0 * gamma);
+                let t = zeta.signum() / (zeta.abs() + (1.0 + zeta * zeta).sqrt());
+                let c = 1.0 / (1.0 + t * t).sqrt();
+                let s = c * t;
+                for m in &mut [&mut cols, &mut v] {
+                    let (left, right) = m.split_at_mut(q);
+                    for (x, y) in left[p].iter_mut().zip(right[0].iter_mut()) {
+                        let (xp, yq) = (*x, *y);
+                        *x = c * xp - s * yq;
+                        *y = s * xp + c * yq;
+                    }
+                }
+            }
+        }
+        if !rotated {
+            break;
+        }
+    }
+
+    let mut order = (0..n).map(|j| (dot(&cols[j], &cols[j]).sqrt(), j)).collect::<Vec<_>>();
+    order.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap());
+    let s = order.iter().map(|&(s, _)| s).collect();
+    let u = order
+        .iter()
+        .map(|&(s, j)| cols[j].iter().map(|x| if s > 0.0 { x / s } else { 0.0 }).collect())
+        .collect();
+    let v = order.iter().map(|&(_, j)| v[j].clone()).collect();
+
+    Ok(Svd { u, s, v })
+}
+
+impl Svd {
+    pub fn singular_values(&self) -> &[f64] {
+        &self.s
+    }
+
+    pub fn u(&self) -> &[Vec<f64>] {
+        &self.u
+    }
+
+    pub fn v(&self) -> &[Vec<f64>] {
+        &self.v
+    }
+
+    // s_max / s_min; infinite for a singular matrix.
+    pub fn condition_number(&self) -> f64 {
+        let last = self.s[self.s.len() - 1];
+        if last > 0.0 { self.s[0] / last } else { f64::INFINITY }
+    }
+
+    // Number of singular values above rcond * s_max.
+    pub fn rank(&self, rcond: f64) -> usize {
+        self.s.iter().filter(|&&s| s > rcond * self.s[0]).count()
+    }
+
+    // Minimum norm least-squares solution with the singular values at or
+    // below rcond * s_max treated as zero.
+    pub fn solve(&self, b: &[f64], rcond: f64) -> Result<Vec<f64>, LinalgError> {
+        let m = self.u[0].len();
+        if b.len() != m {
+            return Err(LinalgError::DimensionMismatch { what: "right-hand side", expected: m, found: b.len() });
+        }
+
+        let mut x = vec![0.0; self.v.len()];
+        for ((u, v), &s) in self.u.iter().zip(self.v.iter()).zip(self.s.iter()).take(self.rank(rcond)) {
+            let c = dot(u, b) / s;
+            for (xi, vi) in x.iter_mut().zip(v.iter()) {
+                *xi += c * vi;
+            }
+        }
+
+        Ok(x)
+    }
+}
+
+// Default relative cutoff for small singular values, as in LAPACK gelss
+// drivers: machine epsilon times the larger dimension.
+pub fn default_rcond(m: usize, n: usize) -> f64 {
+    f64::EPSILON * m.max(n) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn multiply(a: &[f64], b: &[f64], c: &[f64], x: &[f64]) -> Vec<f64> {
+        let n = b.len();
+        (0..n)
+            .map(|i| {
+                let mut res = b[i] * x[i];
+                if i > 0 {
+                    res += a[i - 1] * x[i - 1];
+                }
+                if i < n - 1 {
+                    res += c[i] * x[i + 1];
+                }
+                res
+            })
+            .collect()
+    }
+
+    fn assert_close(res: &[f64], expected: &[f64]) {
+        for (r, e) in res.iter().zip(expected.iter()) {
+            assert!((r - e).abs() < 1e-12, "{:?} != {:?}", res, expected);
+        }
+    }
+
+    #[test]
+    fn thomas_algorithm() {
+        let a = [1.0, -0.5, 2.0, 0.3];
+        let b = [4.0, 5.0, 6.0, 5.5, 4.5];
+        let c = [0.7, 1.1, -1.0, 0.9];
+        let x = [1.0, -2.0, 0.5, 3.0, -1.5];
+        let d = multiply(&a, &b, &c, &x);
+
+        assert_close(&solve_tridiagonal(&a, &b, &c, &d).unwrap(), &x);
+        assert_close(&solve_tridiagonal_with(&a, &b, &c, &d, Pivoting::Always).unwrap(), &x);
+        assert_close(&solve_tridiagonal(&[], &[2.0], &[], &[3.0]).unwrap(), &[1.5]);
+    }
+
+    #[test]
+    fn dimension_checks() {
+        assert_eq!(solve_tridiagonal(&[], &[], &[], &[]), Err(LinalgError::Empty));
+        assert_eq!(solve_tridiagonal(&[1.0], &[1.0, 2.0, 3.0], &[1.0, 1.0], &[1.0, 1.0, 1.0]),
+                   Err(LinalgError::DimensionMismatch { what: "lower diagonal", expected: 2, found: 1 }));
+        assert_eq!(solve_tridiagonal(&[1.0, 1.0], &[1.0, 2.0, 3.0], &[1.0, 1.0], &[1.0, 1.0]),
+                   Err(LinalgError::DimensionMismatch { what: "right-hand side", expected: 3, found: 2 }));
+    }
+
+    #[test]
+    fn bad_pivots() {
+        // Nonsingular, but the first pivot is zero.
+        let a = [1.0, 1.0];
+        let b = [0.0, 1.0, 3.0];
+        let c = [2.0, 1.0];
+        let x = [1.0, 2.0, -1.0];
+        let d = multiply(&a, &b, &c, &x);
+
+        assert_eq!(solve_tridiagonal(&a, &b, &c, &d), Err(LinalgError::Singular { row: 0 }));
+        assert_close(&solve_tridiagonal_with(&a, &b, &c, &d, Pivoting::Fallback).unwrap(), &x);
+        assert_close(&solve_tridiagonal_with(&a, &b, &c, &d, Pivoting::Always).unwrap(), &x);
+
+        // Tiny pivot in the second row after elimination.
+        let b = [1.0, 1.0 + 1e-10, 3.0];
+        let c = [1.0, 1.0];
+        let d = multiply(&a, &b, &c, &x);
+        match solve_tridiagonal(&a, &b, &c, &d) {
+            Err(LinalgError::IllConditioned { row: 1, .. }) => (),
+            res => panic!("unexpected result {:?}", res),
+        }
+        let res = solve_tridiagonal_with(&a, &b, &c, &d, Pivoting::Fallback).unwrap();
+        for (r, e) in res.iter().zip(x.iter()) {
+            assert!((r - e).abs() < 1e-5);
+        }
+
+        // Truly singular: the second row repeats the first.
+        let b = [1.0, 1.0, 3.0];
+        let c = [1.0, 0.0];
+        assert_eq!(solve_tridiagonal_with(&a, &b, &c, &d, Pivoting::Always),
+                   Err(LinalgError::Singular { row: 2 }));
+    }
+
+    fn sample_banded() -> SymmetricBanded {
+        let n = 7;
+        let mut a = SymmetricBanded::zeros(n, 2);
+        for i in 0..n {
+            a.add(i, i, 6.0 + i as f64);
+            if i + 1 < n {
+                a.add(i, i + 1, -2.0 + 0.1 * i as f64);
+            }
+            if i + 2 < n {
+                a.add(i + 2, i, 0.5);
+            }
+        }
+        a
+    }
+
+    fn dense(a: &SymmetricBanded) -> Vec<Vec<f64>> {
+        let n = a.size();
+        (0..n).map(|i| (0..n).map(|j| a.get(i, j)).collect()).collect()
+    }
+
+    #[test]
+    fn banded_ldl() {
+        let a = sample_banded();
+        let x = [1.0, -1.0, 2.0, 0.5, -3.0, 1.5, 0.25];
+        let rhs = dense(&a).iter().map(|row| row.iter().zip(x.iter()).map(|(a, x)| a * x).sum()).collect::<Vec<f64>>();
+
+        let ldl = a.ldl().unwrap();
+        assert_close(&ldl.solve(&rhs).unwrap(), &x);
+
+        // Every column of the inverse, compared with the band from inverse_band.
+        let inv = ldl.inverse_band();
+        let n = a.size();
+        for j in 0..n {
+            let mut e = vec![0.0; n];
+            e[j] = 1.0;
+            let col = ldl.solve(&e).unwrap();
+            for (i, c) in col.iter().enumerate() {
+                if (i as i64 - j as i64).abs() <= 2 {
+                    assert!((inv.get(i, j) - c).abs() < 1e-14);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn banded_ldl_not_positive_definite() {
+        let mut a = SymmetricBanded::zeros(3, 1);
+        a.add(0, 0, 1.0);
+        a.add(0, 1, 2.0);
+        a.add(1, 1, 1.0);
+        a.add(2, 2, 1.0);
+        assert_eq!(a.ldl().unwrap_err(), LinalgError::Singular { row: 1 });
+    }
+
+    #[test]
+    fn cyclic_tridiagonal() {
+        let a = [1.0, -0.5, 2.0, 0.3];
+        let b = [4.0, 5.0, 6.0, 5.5, 4.5];
+        let c = [0.7, 1.1, -1.0, 0.9];
+        let (alpha, beta) = (0.8, -1.2);
+        let x = [1.0, -2.0, 0.5, 3.0, -1.5];
+
+        let n = b.len();
+        let mut d = multiply(&a, &b, &c, &x);
+        d[0] += beta * x[n - 1];
+        d[n - 1] += alpha * x[0];
+
+        assert_close(&solve_cyclic_tridiagonal(&a, &b, &c, &d, alpha, beta).unwrap(), &x);
+    }
+
+    fn sample_dense() -> Vec<Vec<f64>> {
+        vec![vec![1.0, 2.0, 0.5], vec![-1.0, 0.3, 2.0], vec![0.0, 1.5, -1.0], vec![2.0, -0.7, 0.1], vec![0.4, 0.0, 1.0]]
+    }
+
+    #[test]
+    fn householder_qr() {
+        let a = sample_dense();
+        let qr = qr_decompose(&a).unwrap();
+        let r = qr.r();
+        // Q^T*A = R, with zeros below the first three rows.
+        for j in 0..3 {
+            let col = a.iter().map(|row| row[j]).collect::<Vec<_>>();
+            let qt_col = qr.apply_qt(&col).unwrap();
+            for i in 0..5 {
+                let expected = if i < 3 { r[i][j] } else { 0.0 };
+                assert!((qt_col[i] - expected).abs() < 1e-14);
+            }
+        }
+
+        // The residual of the least-squares solution is orthogonal to the columns.
+        let b = [1.0, 0.0, -2.0, 0.5, 3.0];
+        let x = qr.solve_least_squares(&b).unwrap();
+        let res = a.iter().zip(b.iter()).map(|(row, bi)| bi - dot(row, &x)).collect::<Vec<_>>();
+        for j in 0..3 {
+            assert!(a.iter().zip(res.iter()).map(|(row, r)| row[j] * r).sum::<f64>().abs() < 1e-13);
+        }
+
+        let deficient = a.iter().map(|row| vec![row[0], row[1], row[0] - 2.0 * row[1]]).collect::<Vec<_>>();
+        assert_eq!(qr_decompose(&deficient).unwrap().solve_least_squares(&b), Err(LinalgError::Singular { row: 2 }));
+        assert_eq!(qr_decompose(&a[..2]).unwrap_err(),
+                   LinalgError::DimensionMismatch { what: "matrix column", expected: 3, found: 2 });
+        assert_eq!(qr_decompose(&[vec![1.0, 2.0], vec![1.0]]).unwrap_err(),
+                   LinalgError::DimensionMismatch { what: "matrix row", expected: 2, found: 1 });
+    }
+
+    #[test]
+    fn jacobi_svd() {
+        let a = sample_dense();
+        let svd = svd(&a).unwrap();
+        let s = svd.singular_values();
+        assert!(s.windows(2).all(|w| w[0] >= w[1]));
+        for (i, row) in a.iter().enumerate() {
+            for (j, &aij) in row.iter().enumerate() {
+                let usv = (0..3).map(|k| svd.u()[k][i] * s[k] * svd.v()[k][j]).sum::<f64>();
+                assert!((usv - aij).abs() < 1e-13);
+            }
+        }
+        for p in 0..3 {
+            for q in 0..3 {
+                let expected = if p == q { 1.0 } else { 0.0 };
+                assert!((dot(&svd.u()[p], &svd.u()[q]) - expected).abs() < 1e-13);
+                assert!((dot(&svd.v()[p], &svd.v()[q]) - expected).abs() < 1e-13);
+            }
+        }
+
+        // Same least-squares solution as QR for a full rank matrix.
+        let b = [1.0, 0.0, -2.0, 0.5, 3.0];
+        let x_qr = qr_decompose(&a).unwrap().solve_least_squares(&b).unwrap();
+        let x_svd = svd.solve(&b, default_rcond(5, 3)).unwrap();
+        assert_close(&x_svd, &x_qr);
+        assert!((svd.condition_number() - s[0] / s[2]).abs() < 1e-12);
+    }
+
+    #[test]
+    fn svd_rank_deficient() {
+        // Third column is a combination of the first two: rank 2, and the
+        // truncated solution is the minimum norm one, orthogonal to the null
+        // vector (1, -2, -1).
+        let a = sample_dense().iter().map(|row| vec![row[0], row[1], row[0] - 2.0 * row[1]]).collect::<Vec<_>>();
+        let svd = svd(&a).unwrap();
+        assert_eq!(svd.rank(default_rcond(5, 3)), 2);
+        assert!(svd.condition_number() > 1e14);
+        let x = svd.solve(&[1.0, 0.0, -2.0, 0.5, 3.0], 1e-10).unwrap();
+        assert!((x[0] - 2.0 * x[1] - x[2]).abs() < 1e-12);
+    }
+}
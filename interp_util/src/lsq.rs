@@ -0,0 +1,421 @@
+use std::error::Error;
+use std::fmt;
+use std::rc::Rc;
+
+use linalg::{default_rcond, qr_decompose, svd, LinalgError};
+use stats::{student_t_cdf, student_t_quantile};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FitError {
+    NoBasis,
+    // At least as many points as basis functions are needed.
+    TooFewPoints { needed: usize, found: usize },
+    // NaN or infinite coordinate in the input point with this index.
+    NotFinite { index: usize },
+    // Weights do not match the points in number, or one of them is not
+    // positive and finite.
+    InvalidWeights,
+    // The design matrix has fewer than basis_len independent columns; the
+    // QR solver refuses such fits.
+    RankDeficient { rank: usize, condition: f64 },
+    // Cross-validation needs 2 <= folds <= points.
+    InvalidFolds { folds: usize, points: usize },
+    // RANSAC inlier threshold that is negative or not finite.
+    InvalidThreshold { threshold: f64 },
+    // RANSAC with zero iterations draws no sample to fit.
+    NoIterations,
+    Linalg(LinalgError),
+}
+
+impl fmt::Display for FitError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            FitError::NoBasis => write!(f, "no basis functions"),
+            FitError::TooFewPoints { needed, found } => write!(f, "fit needs at least {} points, got {}", needed, found),
+            FitError::NotFinite { index } => write!(f, "point #{} is not finite", index),
+            FitError::InvalidWeights => write!(f, "invalid weights"),
+            FitError::RankDeficient { rank, condition } => {
+                write!(f, "design matrix has rank {} (condition number {:e})", rank, condition)
+            }
+            FitError::InvalidFolds { folds, points } => write!(f, "cannot split {} points into {} folds", points, folds),
+            FitError::InvalidThreshold { threshold } => write!(f, "invalid inlier threshold {}", threshold),
+            FitError::NoIterations => write!(f, "no RANSAC iterations"),
+            FitError::Linalg(ref err) => write!(f, "least squares: {}", err),
+        }
+    }
+}
+
+impl Error for FitError {}
+
+impl From<LinalgError> for FitError {
+    fn from(err: LinalgError) -> FitError {
+        FitError::Linalg(err)
+    }
+}
+
+type BasisFn<'a> = Rc<dyn Fn(f64) -> f64 + 'a>;
+
+// How LeastSquares solves for the coefficients. Both factor the weighted
+// design matrix, so the conditioning is not squared as with the normal
+// equations.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Solver {
+    // Householder QR; fails with RankDeficient when the matrix is
+    // numerically rank deficient.
+    Qr,
+    // Truncated SVD: singular values at or below rcond * s_max are dropped
+    // and the minimum norm solution is returned.
+    Svd { rcond: f64 },
+}
+
+// Linear least-squares problem: the model is sum(c[j]*f[j](x)) over the
+// basis functions f[j], added one by one with with_basis.
+#[derive(Clone)]
+pub struct LeastSquares<'a> {
+    basis: Vec<BasisFn<'a>>,
+    solver: Solver,
+}
+
+impl<'a> Default for LeastSquares<'a> {
+    fn default() -> LeastSquares<'a> {
+        LeastSquares::new()
+    }
+}
+
+impl<'a> LeastSquares<'a> {
+    // No basis functions yet; solves with Solver::Qr.
+    pub fn new() -> LeastSquares<'a> {
+        LeastSquares {
+            basis: Vec::new(),
+            solver: Solver::Qr,
+        }
+    }
+
+    pub fn with_solver(mut self, solver: Solver) -> LeastSquares<'a> {
+        self.solver = solver;
+        self
+    }
+
+    pub fn with_basis<F: Fn(f64) -> f64 + 'a>(mut self, f: F) -> LeastSquares<'a> {
+        self.basis.push(Rc::new(f));
+        self
+    }
+
+    pub fn basis_len(&self) -> usize {
+        self.basis.len()
+    }
+
+    // Minimizes sum(w[i]*(y[i] - sum(c[j]*f[j](x[i])))^2), the rows of the
+    // design matrix and y scaled by sqrt(w[i]). Weights default to 1. The rank
+    // and the condition number come from the SVD of the triangular factor.
+    pub fn fit(&self, pts: &[(f64, f64)], weights: Option<&[f64]>) -> Result<LinearFit<'a>, FitError> {
+        let k = self.basis.len();
+        if k == 0 {
+            return Err(FitError::NoBasis);
+        }
+        if pts.len() < k {
+            return Err(FitError::TooFewPoints { needed: k, found: pts.len() });
+        }
+        if let Some(index) = pts.iter().position(|&(x, y)| !x.is_finite() || !y.is_finite()) {
+            return Err(FitError::NotFinite { index });
+        }
+        if let Some(w) = weights {
+            if w.len() != pts.len() || w.iter().any(|&w| !(w.is_finite() && w > 0.0)) {
+                return Err(FitError::InvalidWeights);
+            }
+        }
+        let weight = |i: usize| weights.map_or(1.0, |w| w[i]);
+
+        let design = pts.iter()
+            .enumerate()
+            .map(|(i, &(x, _))| self.basis.iter().map(|f| weight(i).sqrt() * f(x)).collect())
+            .collect::<Vec<Vec<f64>>>();
+        let rhs = pts.iter().enumerate().map(|(i, &(_, y))| weight(i).sqrt() * y).collect::<Vec<_>>();
+
+        let qr = qr_decompose(&design)?;
+        let r_svd = svd(&qr.r())?;
+        let rank = r_svd.rank(default_rcond(pts.len(), k));
+        let condition = r_svd.condition_number();
+        let coefs = match self.solver {
+            Solver::Qr if rank < k => return Err(FitError::RankDeficient { rank, condition }),
+            Solver::Qr => qr.solve_least_squares(&rhs)?,
+            Solver::Svd { rcond } => r_svd.solve(&qr.apply_qt(&rhs)?[..k], rcond)?,
+        };
+        let rank = match self.solver {
+            Solver::Qr => rank,
+            Solver::Svd { rcond } => r_svd.rank(rcond),
+        };
+
+        // (A^T*W*A)^-1 = (R^T*R)^-1 = V*S^-2*V^T over the kept singular values.
+        let mut cov_unscaled = vec![vec![0.0; k]; k];
+        for (v, s) in r_svd.v().iter().zip(r_svd.singular_values()).take(rank) {
+            for (i, row) in cov_unscaled.iter_mut().enumerate() {
+                for (j, c) in row.iter_mut().enumerate() {
+                    *c += v[i] * v[j] / (s * s);
+                }
+            }
+        }
+
+        let mut res = LinearFit {
+            basis: self.basis.clone(),
+            coefs,
+            rank,
+            condition,
+            cov_unscaled,
+            residuals: Vec::new(),
+            rss: 0.0,
+            tss: 0.0,
+        };
+        res.residuals = pts.iter().map(|&(x, y)| y - res.calc(x)).collect();
+        res.rss = res.residuals.iter().enumerate().map(|(i, r)| weight(i) * r * r).sum();
+        let w_sum = (0..pts.len()).map(weight).sum::<f64>();
+        let mean = pts.iter().enumerate().map(|(i, p)| weight(i) * p.1).sum::<f64>() / w_sum;
+        res.tss = pts.iter().enumerate().map(|(i, p)| weight(i) * (p.1 - mean).powi(2)).sum();
+
+        Ok(res)
+    }
+}
+
+// Polynomial basis 1, x, ..., x^degree.
+pub fn polynomial_basis(degree: usize) -> LeastSquares<'static> {
+    (0..degree + 1).fold(LeastSquares::new(), |lsq, k| lsq.with_basis(move |x: f64| x.powi(k as i32)))
+}
+
+#[derive(Clone)]
+pub struct LinearFit<'a> {
+    basis: Vec<BasisFn<'a>>,
+    coefs: Vec<f64>,
+    rank: usize,
+    condition: f64,
+    cov_unscaled: Vec<Vec<f64>>,
+    residuals: Vec<f64>,
+    rss: f64,
+    tss: f64,
+}
+
+impl<'a> LinearFit<'a> {
+    pub fn coefficients(&self) -> &[f64] {
+        &self.coefs
+    }
+
+    // Effective rank of the weighted design matrix: for Solver::Svd the
+    // number of singular values kept.
+    pub fn rank(&self) -> usize {
+        self.rank
+    }
+
+    // Ratio of the largest to the smallest singular value of the weighted
+    // design matrix.
+    pub fn condition_number(&self) -> f64 {
+        self.condition
+    }
+
+    // y[i] - fit(x[i]) in the order of the input points.
+    pub fn residuals(&self) -> &[f64] {
+        &self.residuals
+    }
+
+    // Weighted residual sum of squares.
+    pub fn rss(&self) -> f64 {
+        self.rss
+    }
+
+    // 1 - rss/tss, with tss taken around the weighted mean of y. NaN if all
+    // ordinates are equal.
+    pub fn r_squared(&self) -> f64 {
+        1.0 - self.rss / self.tss
+    }
+
+    pub fn calc(&self, x: f64) -> f64 {
+        self.basis.iter().zip(self.coefs.iter()).map(|(f, c)| c * f(x)).sum()
+    }
+
+    // Residual degrees of freedom, n - rank.
+    pub fn dof(&self) -> usize {
+        self.residuals.len() - self.rank
+    }
+
+    // Residual variance estimate rss/dof; NaN for an exact fit with dof = 0.
+    pub fn sigma2(&self) -> f64 {
+        if self.dof() == 0 { f64::NAN } else { self.rss / self.dof() as f64 }
+    }
+
+    // Coefficient covariance sigma2*(A^T*W*A)^-1, with the pseudo-inverse
+    // over the kept singular values for Solver::Svd.
+    pub fn covariance(&self) -> Vec<Vec<f64>> {
+        let sigma2 = self.sigma2();
+        self.cov_unscaled.iter().map(|row| row.iter().map(|c| sigma2 * c).collect()).collect()
+    }
+
+    pub fn standard_errors(&self) -> Vec<f64> {
+        let sigma2 = self.sigma2();
+        (0..self.coefs.len()).map(|j| (sigma2 * self.cov_unscaled[j][j]).sqrt()).collect()
+    }
+
+    // c[j]/se[j], for the hypothesis c[j] = 0.
+    pub fn t_statistics(&self) -> Vec<f64> {
+        self.coefs.iter().zip(self.standard_errors()).map(|(c, se)| c / se).collect()
+    }
+
+    // Two-sided p-values of the t statistics with dof degrees of freedom.
+    pub fn p_values(&self) -> Vec<f64> {
+        let dof = self.dof() as f64;
+        self.t_statistics().iter().map(|t| 2.0 * student_t_cdf(-t.abs(), dof)).collect()
+    }
+
+    // Pointwise interval (lower, upper) at confidence level `level` (e.g.
+    // 0.95) for the mean response at x: fit(x) -+ t*sqrt(sigma2*a^T*C*a), with
+    // a[j] = f[j](x) and C the unscaled covariance.
+    pub fn confidence_band(&self, x: f64, level: f64) -> (f64, f64) {
+        self.band(x, level, 0.0)
+    }
+
+    // Same as confidence_band for a new observation of unit weight at x,
+    // which adds sigma2 under the root.
+    pub fn prediction_band(&self, x: f64, level: f64) -> (f64, f64) {
+        self.band(x, level, 1.0)
+    }
+
+    fn band(&self, x: f64, level: f64, new_obs: f64) -> (f64, f64) {
+        let a = self.basis.iter().map(|f| f(x)).collect::<Vec<_>>();
+        let quad = self.cov_unscaled
+            .iter()
+            .zip(a.iter())
+            .map(|(row, ai)| ai * row.iter().zip(a.iter()).map(|(c, aj)| c * aj).sum::<f64>())
+            .sum::<f64>();
+        let half = student_t_quantile(0.5 + 0.5 * level, self.dof() as f64) * (self.sigma2() * (quad + new_obs)).sqrt();
+        let y = self.calc(x);
+        (y - half, y + half)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tests::noise;
+
+    #[test]
+    fn exact_polynomial() {
+        let pts = ::linspace(-1.0, 3.0, 20).iter().map(|&x| (x, 2.0 - x + 0.5 * x * x * x)).collect::<Vec<_>>();
+        let fit = polynomial_basis(3).fit(&pts, None).unwrap();
+        for (c, e) in fit.coefficients().iter().zip(&[2.0, -1.0, 0.0, 0.5]) {
+            assert!((c - e).abs() < 1e-10);
+        }
+        assert!(fit.rss() < 1e-20);
+        assert!((fit.r_squared() - 1.0).abs() < 1e-12);
+        assert!((fit.calc(4.0) - 30.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn arbitrary_basis() {
+        let f = |x: f64| 1.5 * x.sin() - 0.3 * (2.0 * x).cos();
+        let pts = ::linspace(0.0, 6.0, 60).iter().enumerate().map(|(i, &x)| (x, f(x) + 0.1 * noise(i))).collect::<Vec<_>>();
+        let omega = 2.0;
+        let fit = LeastSquares::new()
+            .with_basis(|x: f64| x.sin())
+            .with_basis(|x: f64| (omega * x).cos())
+            .fit(&pts, None)
+            .unwrap();
+        assert!((fit.coefficients()[0] - 1.5).abs() < 0.02);
+        assert!((fit.coefficients()[1] + 0.3).abs() < 0.02);
+        assert!(fit.r_squared() > 0.99 && fit.r_squared() < 1.0);
+        let rss = fit.residuals().iter().map(|r| r * r).sum::<f64>();
+        assert!((rss - fit.rss()).abs() < 1e-12);
+    }
+
+    #[test]
+    fn weights() {
+        // A constant fit is the weighted mean.
+        let pts = [(0.0, 1.0), (1.0, 2.0), (2.0, 6.0)];
+        let fit = polynomial_basis(0).fit(&pts, Some(&[1.0, 2.0, 1.0])).unwrap();
+        assert!((fit.coefficients()[0] - 2.75).abs() < 1e-14);
+        assert_eq!(fit.r_squared(), 0.0);
+    }
+
+    #[test]
+    fn invalid_input() {
+        let pts = [(0.0, 1.0), (1.0, 2.0)];
+        assert_eq!(LeastSquares::new().fit(&pts, None).err(), Some(FitError::NoBasis));
+        assert_eq!(polynomial_basis(2).fit(&pts, None).err(), Some(FitError::TooFewPoints { needed: 3, found: 2 }));
+        assert_eq!(polynomial_basis(1).fit(&[(0.0, 1.0), (f64::NAN, 2.0)], None).err(),
+                   Some(FitError::NotFinite { index: 1 }));
+        assert_eq!(polynomial_basis(1).fit(&pts, Some(&[1.0, -1.0])).err(), Some(FitError::InvalidWeights));
+    }
+
+    #[test]
+    fn rank_deficient_basis() {
+        // x and 2x span the same space.
+        let twice = LeastSquares::new().with_basis(|x| x).with_basis(|x| 2.0 * x);
+        let pts = [(1.0, 1.0), (2.0, 2.0), (3.0, 2.0)];
+        match twice.fit(&pts, None) {
+            Err(FitError::RankDeficient { rank: 1, condition }) => assert!(condition > 1e14),
+            other => panic!("collinear basis accepted: {:?}", other.map(|fit| fit.coefficients().to_vec())),
+        }
+
+        // The SVD solver gives the minimum norm solution, c0 = 2*c1.
+        let fit = twice.with_solver(Solver::Svd { rcond: 1e-10 }).fit(&pts, None).unwrap();
+        assert_eq!(fit.rank(), 1);
+        let c = fit.coefficients();
+        assert!((c[0] - 0.5 * c[1]).abs() < 1e-12);
+        assert!((fit.calc(2.0) - 2.0 * (1.0 + 4.0 + 6.0) / 14.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn line_statistics() {
+        // Textbook formulas for y = c0 + c1*x: se(c1) = s/sqrt(sxx),
+        // se(c0) = s*sqrt(1/n + mean^2/sxx), and the confidence half-width
+        // t*s*sqrt(1/n + (x - mean)^2/sxx).
+        let pts = (0..12).map(|i| (i as f64 * 0.5, 1.0 + 0.8 * i as f64 * 0.5 + noise(i))).collect::<Vec<_>>();
+        let fit = polynomial_basis(1).fit(&pts, None).unwrap();
+        let n = pts.len() as f64;
+        let mean = pts.iter().map(|p| p.0).sum::<f64>() / n;
+        let sxx = pts.iter().map(|p| (p.0 - mean).powi(2)).sum::<f64>();
+        let s2 = fit.rss() / (n - 2.0);
+
+        assert_eq!(fit.dof(), 10);
+        assert!((fit.sigma2() - s2).abs() < 1e-14);
+        let se = fit.standard_errors();
+        assert!((se[1] - (s2 / sxx).sqrt()).abs() < 1e-12);
+        assert!((se[0] - (s2 * (1.0 / n + mean * mean / sxx)).sqrt()).abs() < 1e-12);
+        let cov = fit.covariance();
+        assert!((cov[0][1] + s2 * mean / sxx).abs() < 1e-12);
+        assert!((cov[0][1] - cov[1][0]).abs() < 1e-15);
+        let t = fit.t_statistics();
+        assert!((t[1] - fit.coefficients()[1] / se[1]).abs() < 1e-12);
+        assert!(fit.p_values()[1] < 1e-6);
+
+        let t975 = 2.228_138_851_986_274;
+        for &x in &[0.0, 2.7, 7.0] {
+            let (lo, hi) = fit.confidence_band(x, 0.95);
+            let half = t975 * (s2 * (1.0 / n + (x - mean).powi(2) / sxx)).sqrt();
+            assert!((hi - lo - 2.0 * half).abs() < 1e-9);
+            assert!((0.5 * (lo + hi) - fit.calc(x)).abs() < 1e-12);
+
+            let (plo, phi) = fit.prediction_band(x, 0.95);
+            let half = t975 * (s2 * (1.0 + 1.0 / n + (x - mean).powi(2) / sxx)).sqrt();
+            assert!((phi - plo - 2.0 * half).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn exact_fit_has_no_variance() {
+        let fit = polynomial_basis(1).fit(&[(0.0, 1.0), (1.0, 3.0)], None).unwrap();
+        assert_eq!(fit.dof(), 0);
+        assert!(fit.sigma2().is_nan());
+        assert!(fit.confidence_band(0.5, 0.95).0.is_nan());
+    }
+
+    #[test]
+    fn high_degree_monomials() {
+        // Degree 8 on [0, 5]: the normal equations would have a condition
+        // number far beyond 1/eps, the QR solution still reproduces the data.
+        let f = |x: f64| (0.7 * x).sin();
+        let pts = ::linspace(0.0, 5.0, 60).iter().map(|&x| (x, f(x))).collect::<Vec<_>>();
+        let fit = polynomial_basis(8).fit(&pts, None).unwrap();
+        assert_eq!(fit.rank(), 9);
+        assert!(fit.condition_number() > 1e6 && fit.condition_number() < 1e12);
+        for &(x, y) in &pts {
+            assert!((fit.calc(x) - y).abs() < 1e-6);
+        }
+    }
+}
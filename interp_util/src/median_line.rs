@@ -0,0 +1,117 @@
+use lsq::FitError;
+use stats::{mad, median};
+
+// Straight line y = slope*x + intercept from a rank-based estimator.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LineEstimate {
+    slope: f64,
+    intercept: f64,
+    residuals: Vec<f64>,
+}
+
+impl LineEstimate {
+    pub fn slope(&self) -> f64 {
+        self.slope
+    }
+
+    pub fn intercept(&self) -> f64 {
+        self.intercept
+    }
+
+    pub fn calc(&self, x: f64) -> f64 {
+        self.slope * x + self.intercept
+    }
+
+    // y[i] - (slope*x[i] + intercept) for all input points.
+    pub fn residuals(&self) -> &[f64] {
+        &self.residuals
+    }
+
+    // Robust residual scale, 1.4826 * MAD of the residuals.
+    pub fn scale(&self) -> f64 {
+        1.4826 * mad(&self.residuals)
+    }
+
+    // Indices of the points with |residual| <= threshold.
+    pub fn inliers(&self, threshold: f64) -> Vec<usize> {
+        (0..self.residuals.len()).filter(|&i| self.residuals[i].abs() <= threshold).collect()
+    }
+}
+
+// Theil-Sen estimator: the slope is the median of the slopes between all
+// pairs of points with distinct abscissae, the intercept the median of
+// y - slope*x. Breakdown point about 29%; O(n^2) time and memory.
+pub fn theil_sen(pts: &[(f64, f64)]) -> Result<LineEstimate, FitError> {
+    check_line_points(pts)?;
+    let mut slopes = Vec::new();
+    for (i, p) in pts.iter().enumerate() {
+        slopes.extend(pts[i + 1..].iter().filter(|q| q.0 != p.0).map(|q| (q.1 - p.1) / (q.0 - p.0)));
+    }
+    line_from_slope(pts, median(&slopes))
+}
+
+// Siegel's repeated median: for every point the median of its slopes to the
+// others, and the slope is the median of those. Breakdown point 50%.
+pub fn repeated_median(pts: &[(f64, f64)]) -> Result<LineEstimate, FitError> {
+    check_line_points(pts)?;
+    let slopes = pts.iter()
+        .map(|p| {
+            let own = pts.iter().filter(|q| q.0 != p.0).map(|q| (q.1 - p.1) / (q.0 - p.0)).collect::<Vec<_>>();
+            median(&own)
+        })
+        .filter(|s| !s.is_nan())
+        .collect::<Vec<_>>();
+    line_from_slope(pts, median(&slopes))
+}
+
+fn check_line_points(pts: &[(f64, f64)]) -> Result<(), FitError> {
+    if pts.len() < 2 {
+        return Err(FitError::TooFewPoints { needed: 2, found: pts.len() });
+    }
+    if let Some(index) = pts.iter().position(|&(x, y)| !x.is_finite() || !y.is_finite()) {
+        return Err(FitError::NotFinite { index });
+    }
+    Ok(())
+}
+
+fn line_from_slope(pts: &[(f64, f64)], slope: f64) -> Result<LineEstimate, FitError> {
+    if slope.is_nan() {
+        // All the abscissae are equal.
+        return Err(FitError::RankDeficient { rank: 1, condition: f64::INFINITY });
+    }
+    let intercept = median(&pts.iter().map(|&(x, y)| y - slope * x).collect::<Vec<_>>());
+    let residuals = pts.iter().map(|&(x, y)| y - slope * x - intercept).collect();
+    Ok(LineEstimate { slope, intercept, residuals })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tests::contaminated_line;
+
+    #[test]
+    fn rank_estimators() {
+        let (pts, outliers) = contaminated_line();
+        for estimate in &[theil_sen(&pts).unwrap(), repeated_median(&pts).unwrap()] {
+            assert!((estimate.slope() - 1.5).abs() < 0.02, "{}", estimate.slope());
+            assert!((estimate.intercept() - 1.0).abs() < 0.5, "{}", estimate.intercept());
+            let inliers = estimate.inliers(5.0 * estimate.scale());
+            assert_eq!(inliers.len(), 97);
+            assert!(outliers.iter().all(|i| !inliers.contains(i)));
+        }
+
+        // Exact for a line, also with repeated abscissae.
+        let pts = [(0.0, 1.0), (1.0, 3.0), (1.0, 3.0), (2.5, 6.0)];
+        let estimate = repeated_median(&pts).unwrap();
+        assert!((estimate.slope() - 2.0).abs() < 1e-15 && (estimate.intercept() - 1.0).abs() < 1e-15);
+        assert_eq!(theil_sen(&pts).unwrap(), estimate);
+    }
+
+    #[test]
+    fn rank_estimator_errors() {
+        assert_eq!(theil_sen(&[(1.0, 2.0)]), Err(FitError::TooFewPoints { needed: 2, found: 1 }));
+        assert_eq!(repeated_median(&[(1.0, 2.0), (f64::NAN, 1.0)]), Err(FitError::NotFinite { index: 1 }));
+        assert_eq!(theil_sen(&[(1.0, 2.0), (1.0, 3.0)]),
+                   Err(FitError::RankDeficient { rank: 1, condition: f64::INFINITY }));
+    }
+}
@@ -0,0 +1,132 @@
+use lagrange::NodeError;
+
+// Interpolating polynomial in Newton form
+// p(x) = c[0] + c[1]*(x - x[0]) + ... + c[n-1]*(x - x[0])*...*(x - x[n-2])
+// with c[k] = f[x[0], ..., x[k]]. The last row of the divided-difference
+// table, f[x[n-1]], f[x[n-2], x[n-1]], ..., f[x[0], ..., x[n-1]], is kept so a
+// new point costs O(n).
+#[derive(Debug, Clone)]
+pub struct NewtonInterpolant {
+    nodes: Vec<f64>,
+    coefs: Vec<f64>,
+    last_row: Vec<f64>,
+}
+
+impl NewtonInterpolant {
+    pub fn new(pts: &[(f64, f64)]) -> Result<NewtonInterpolant, NodeError> {
+        if pts.is_empty() {
+            return Err(NodeError::NoPoints);
+        }
+
+        let mut res = NewtonInterpolant {
+            nodes: Vec::with_capacity(pts.len()),
+            coefs: Vec::with_capacity(pts.len()),
+            last_row: Vec::with_capacity(pts.len()),
+        };
+        for &(x, y) in pts {
+            res.add_point(x, y)?;
+        }
+
+        Ok(res)
+    }
+
+    // Newton form from an already built divided-difference table, used for the
+    // confluent tables of Hermite interpolation.
+    pub(crate) fn from_table(nodes: Vec<f64>, coefs: Vec<f64>, last_row: Vec<f64>) -> NewtonInterpolant {
+        NewtonInterpolant {
+            nodes,
+            coefs,
+            last_row,
+        }
+    }
+
+    pub fn add_point(&mut self, x: f64, y: f64) -> Result<(), NodeError> {
+        if !x.is_finite() || !y.is_finite() {
+            return Err(NodeError::NotFinite { index: self.nodes.len() });
+        }
+        if self.nodes.contains(&x) {
+            return Err(NodeError::DuplicateNode { x });
+        }
+
+        let n = self.nodes.len();
+        let mut row = Vec::with_capacity(n + 1);
+        row.push(y);
+        for k in 0..n {
+            // f[x[n-k-1], ..., x] from f[x[n-k], ..., x] and f[x[n-k-1], ..., x[n-1]]
+            let dd = (row[k] - self.last_row[k]) / (x - self.nodes[n - k - 1]);
+            row.push(dd);
+        }
+
+        self.coefs.push(row[n]);
+        self.nodes.push(x);
+        self.last_row = row;
+
+        Ok(())
+    }
+
+    pub fn nodes(&self) -> &[f64] {
+        &self.nodes
+    }
+
+    // Divided differences f[x[0]], f[x[0], x[1]], ..., f[x[0], ..., x[n-1]].
+    pub fn coefficients(&self) -> &[f64] {
+        &self.coefs
+    }
+
+    pub fn calc(&self, x: f64) -> f64 {
+        let n = self.coefs.len();
+        let mut res = self.coefs[n - 1];
+        for k in (0..n - 1).rev() {
+            res = res * (x - self.nodes[k]) + self.coefs[k];
+        }
+
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lagrange::LagrangeInterpolant;
+
+    #[test]
+    fn divided_differences() {
+        // f(x) = x^3: f[a, b, c, d] = 1, f[a, b, c] = a + b + c
+        let pts = [0.5, -1.0, 2.0, 3.0, 1.5].iter().map(|&x: &f64| (x, x.powi(3))).collect::<Vec<_>>();
+        let newton = NewtonInterpolant::new(&pts).unwrap();
+        let c = newton.coefficients();
+        assert_eq!(c[0], 0.125);
+        assert!((c[1] - (-1.0f64 - 0.125) / -1.5).abs() < 1e-14);
+        assert!((c[2] - 1.5).abs() < 1e-14);
+        assert!((c[3] - 1.0).abs() < 1e-14);
+        assert!(c[4].abs() < 1e-14);
+        for &x in &[-2.0, 0.0, 0.7, 2.5] {
+            assert!((newton.calc(x) - x * x * x).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn matches_lagrange() {
+        let pts = ::linspace(-1.0, 2.0, 9).iter().map(|&x| (x, (3.0 * x).sin() + x)).collect::<Vec<_>>();
+        let lagrange = LagrangeInterpolant::new(&pts).unwrap();
+        let mut newton = NewtonInterpolant::new(&pts[..3]).unwrap();
+        for &(x, y) in &pts[3..] {
+            newton.add_point(x, y).unwrap();
+        }
+        for &(x, y) in &pts {
+            assert!((newton.calc(x) - y).abs() < 1e-12);
+        }
+        for &x in &[-0.9, 0.1, 1.23, 1.9] {
+            assert!((newton.calc(x) - lagrange.calc(x)).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn invalid_points() {
+        assert_eq!(NewtonInterpolant::new(&[]).unwrap_err(), NodeError::NoPoints);
+        let mut newton = NewtonInterpolant::new(&[(0.0, 1.0), (1.0, 2.0)]).unwrap();
+        assert_eq!(newton.add_point(1.0, 5.0), Err(NodeError::DuplicateNode { x: 1.0 }));
+        assert_eq!(newton.add_point(2.0, f64::INFINITY), Err(NodeError::NotFinite { index: 2 }));
+        assert_eq!(newton.nodes().len(), 2);
+    }
+}
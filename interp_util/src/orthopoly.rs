@@ -0,0 +1,272 @@
+use lsq::{FitError, LeastSquares};
+
+// Legendre polynomial P_n(t) by (k + 1)*P[k+1] = (2k + 1)*t*P[k] - k*P[k-1].
+pub fn legendre(n: usize, t: f64) -> f64 {
+    let (mut prev, mut cur) = (0.0, 1.0);
+    for k in 0..n {
+        let next = ((2 * k + 1) as f64 * t * cur - k as f64 * prev) / (k + 1) as f64;
+        prev = cur;
+        cur = next;
+    }
+    cur
+}
+
+// Chebyshev polynomial of the first kind T_n(t) by T[k+1] = 2t*T[k] - T[k-1].
+pub fn chebyshev(n: usize, t: f64) -> f64 {
+    if n == 0 {
+        return 1.0;
+    }
+    let (mut prev, mut cur) = (1.0, t);
+    for _ in 1..n {
+        let next = 2.0 * t * cur - prev;
+        prev = cur;
+        cur = next;
+    }
+    cur
+}
+
+// Smallest and largest abscissa of the points.
+pub fn data_range(pts: &[(f64, f64)]) -> (f64, f64) {
+    pts.iter().fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), &(x, _)| (lo.min(x), hi.max(x)))
+}
+
+// Affine map of [a, b] onto [-1, 1].
+fn to_unit(a: f64, b: f64) -> impl Fn(f64) -> f64 + Copy {
+    move |x| (2.0 * x - a - b) / (b - a)
+}
+
+// P_0, ..., P_degree of x mapped from [a, b] to [-1, 1].
+pub fn legendre_basis(degree: usize, a: f64, b: f64) -> LeastSquares<'static> {
+    let t = to_unit(a, b);
+    (0..degree + 1).fold(LeastSquares::new(), |lsq, k| lsq.with_basis(move |x| legendre(k, t(x))))
+}
+
+// T_0, ..., T_degree of x mapped from [a, b] to [-1, 1].
+pub fn chebyshev_basis(degree: usize, a: f64, b: f64) -> LeastSquares<'static> {
+    let t = to_unit(a, b);
+    (0..degree + 1).fold(LeastSquares::new(), |lsq, k| lsq.with_basis(move |x| chebyshev(k, t(x))))
+}
+
+// Least-squares polynomial in the basis of polynomials orthogonal over the
+// sample points themselves (Forsythe): p[0] = 1, p[1] = t - alpha[0],
+// p[k+1] = (t - alpha[k])*p[k] - beta[k]*p[k-1], where t is x mapped from the
+// data range to [-1, 1] and
+// alpha[k] = sum(w*t*p[k]^2)/sum(w*p[k]^2), beta[k] = sum(w*p[k]^2)/sum(w*p[k-1]^2).
+// The discrete Gram matrix is diagonal, so each coefficient is a single
+// projection and raise_degree adds a term in O(n) without touching the
+// previous ones.
+#[derive(Debug, Clone)]
+pub struct DiscreteOrthogonalFit {
+    range: (f64, f64),
+    t: Vec<f64>,
+    w: Vec<f64>,
+    // p[k-1] and p[k] at the points, and sum(w*p^2) for both.
+    p_prev: Vec<f64>,
+    p_cur: Vec<f64>,
+    norm_prev: f64,
+    norm_cur: f64,
+    alphas: Vec<f64>,
+    betas: Vec<f64>,
+    coefs: Vec<f64>,
+    residuals: Vec<f64>,
+}
+
+// Fit of the given degree; weights default to 1.
+pub fn fit_discrete_orthogonal(pts: &[(f64, f64)], weights: Option<&[f64]>, degree: usize) -> Result<DiscreteOrthogonalFit, FitError> {
+    if pts.is_empty() {
+        return Err(FitError::TooFewPoints { needed: degree + 1, found: 0 });
+    }
+    if let Some(index) = pts.iter().position(|&(x, y)| !x.is_finite() || !y.is_finite()) {
+        return Err(FitError::NotFinite { index });
+    }
+    if let Some(w) = weights {
+        if w.len() != pts.len() || w.iter().any(|&w| !(w.is_finite() && w > 0.0)) {
+            return Err(FitError::InvalidWeights);
+        }
+    }
+
+    let range = data_range(pts);
+    let t = if range.0 < range.1 {
+        pts.iter().map(|&(x, _)| to_unit(range.0, range.1)(x)).collect()
+    } else {
+        vec![0.0; pts.len()]
+    };
+    let w = weights.map_or_else(|| vec![1.0; pts.len()], |w| w.to_vec());
+    let norm = w.iter().sum::<f64>();
+    let c0 = w.iter().zip(pts.iter()).map(|(w, p)| w * p.1).sum::<f64>() / norm;
+
+    let mut res = DiscreteOrthogonalFit {
+        range,
+        t,
+        p_prev: vec![0.0; pts.len()],
+        p_cur: vec![1.0; pts.len()],
+        norm_prev: 1.0,
+        norm_cur: norm,
+        alphas: Vec::new(),
+        betas: Vec::new(),
+        coefs: vec![c0],
+        residuals: pts.iter().map(|&(_, y)| y - c0).collect(),
+        w,
+    };
+    for _ in 0..degree {
+        res.raise_degree()?;
+    }
+
+    Ok(res)
+}
+
+impl DiscreteOrthogonalFit {
+    pub fn degree(&self) -> usize {
+        self.coefs.len() - 1
+    }
+
+    // Coefficients in the orthogonal basis p[0], ..., p[degree].
+    pub fn coefficients(&self) -> &[f64] {
+        &self.coefs
+    }
+
+    // y[i] - fit(x[i]) in the order of the input points.
+    pub fn residuals(&self) -> &[f64] {
+        &self.residuals
+    }
+
+    // Weighted residual sum of squares.
+    pub fn rss(&self) -> f64 {
+        self.w.iter().zip(self.residuals.iter()).map(|(w, r)| w * r * r).sum()
+    }
+
+    // Adds the next orthogonal polynomial. Fails once the degree reaches the
+    // number of distinct abscissae, when p[k+1] vanishes at every point.
+    pub fn raise_degree(&mut self) -> Result<(), FitError> {
+        let k = self.degree();
+        let alpha = (0..self.t.len()).map(|i| self.w[i] * self.t[i] * self.p_cur[i].powi(2)).sum::<f64>() / self.norm_cur;
+        let beta = if k == 0 { 0.0 } else { self.norm_cur / self.norm_prev };
+
+        let next = (0..self.t.len())
+            .map(|i| (self.t[i] - alpha) * self.p_cur[i] - beta * self.p_prev[i])
+            .collect::<Vec<_>>();
+        let norm = self.w.iter().zip(next.iter()).map(|(w, p)| w * p * p).sum::<f64>();
+        let raw = (0..self.t.len()).map(|i| self.w[i] * ((self.t[i] - alpha) * self.p_cur[i]).powi(2)).sum::<f64>();
+        if norm.is_nan() || norm <= f64::EPSILON * raw {
+            return Err(FitError::RankDeficient { rank: k + 1, condition: f64::INFINITY });
+        }
+
+        // Projection of the current residual, which stays orthogonal to the
+        // lower terms even with round-off.
+        let c = self.w.iter().zip(next.iter()).zip(self.residuals.iter()).map(|((w, p), r)| w * p * r).sum::<f64>() / norm;
+        for (r, p) in self.residuals.iter_mut().zip(next.iter()) {
+            *r -= c * p;
+        }
+
+        self.alphas.push(alpha);
+        self.betas.push(beta);
+        self.coefs.push(c);
+        self.p_prev = ::std::mem::replace(&mut self.p_cur, next);
+        self.norm_prev = self.norm_cur;
+        self.norm_cur = norm;
+
+        Ok(())
+    }
+
+    pub fn calc(&self, x: f64) -> f64 {
+        let (a, b) = self.range;
+        let t = if a < b { to_unit(a, b)(x) } else { 0.0 };
+        let (mut prev, mut cur) = (0.0, 1.0);
+        let mut res = self.coefs[0];
+        for (k, &c) in self.coefs.iter().enumerate().skip(1) {
+            let next = (t - self.alphas[k - 1]) * cur - self.betas[k - 1] * prev;
+            prev = cur;
+            cur = next;
+            res += c * cur;
+        }
+
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lsq::polynomial_basis;
+    use tests::noise;
+
+    fn noisy_series() -> Vec<(f64, f64)> {
+        ::linspace(0.0, 5.0, 100).iter().enumerate().map(|(i, &x)| (x, (1.3 * x).sin() * x + 0.3 * noise(i))).collect()
+    }
+
+    #[test]
+    fn polynomial_values() {
+        assert_eq!(legendre(0, 0.3), 1.0);
+        assert_eq!(legendre(1, 0.3), 0.3);
+        assert!((legendre(2, 0.5) + 0.125).abs() < 1e-15);
+        assert!((legendre(3, 0.5) + 0.4375).abs() < 1e-15);
+        assert!((legendre(7, 1.0) - 1.0).abs() < 1e-14);
+        assert!((chebyshev(3, 0.5) + 1.0).abs() < 1e-15);
+        for &t in &[-0.9, -0.2, 0.4, 1.0] {
+            assert!((chebyshev(9, t) - (9.0 * t.acos()).cos()).abs() < 1e-13);
+        }
+    }
+
+    #[test]
+    fn orthogonal_bases_match_monomials() {
+        let pts = noisy_series();
+        let (a, b) = data_range(&pts);
+        let monomial = polynomial_basis(6).fit(&pts, None).unwrap();
+        let legendre = legendre_basis(6, a, b).fit(&pts, None).unwrap();
+        let chebyshev = chebyshev_basis(6, a, b).fit(&pts, None).unwrap();
+        for &x in &[0.0, 1.1, 2.5, 4.9] {
+            assert!((legendre.calc(x) - monomial.calc(x)).abs() < 1e-9);
+            assert!((chebyshev.calc(x) - monomial.calc(x)).abs() < 1e-9);
+        }
+        assert!(legendre.condition_number() < 10.0);
+        assert!(chebyshev.condition_number() < 10.0);
+        assert!(monomial.condition_number() > 1e5);
+    }
+
+    #[test]
+    fn forsythe_matches_least_squares() {
+        let pts = noisy_series();
+        let w = (0..pts.len()).map(|i| 1.0 + (i % 3) as f64).collect::<Vec<_>>();
+        let fit = fit_discrete_orthogonal(&pts, Some(&w), 7).unwrap();
+        let qr = polynomial_basis(7).fit(&pts, Some(&w)).unwrap();
+        assert_eq!(fit.degree(), 7);
+        for &x in &[0.0, 0.7, 2.5, 3.3, 5.0] {
+            assert!((fit.calc(x) - qr.calc(x)).abs() < 1e-9);
+        }
+        assert!((fit.rss() - qr.rss()).abs() < 1e-9 * qr.rss());
+        for (r, q) in fit.residuals().iter().zip(qr.residuals()) {
+            assert!((r - q).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn raise_degree_keeps_lower_coefficients() {
+        let pts = noisy_series();
+        let mut fit = fit_discrete_orthogonal(&pts, None, 2).unwrap();
+        let low = fit.coefficients().to_vec();
+        let mut rss = fit.rss();
+        for degree in 3..10 {
+            fit.raise_degree().unwrap();
+            assert!(fit.rss() <= rss);
+            rss = fit.rss();
+            let fresh = fit_discrete_orthogonal(&pts, None, degree).unwrap();
+            for (a, b) in fit.coefficients().iter().zip(fresh.coefficients()) {
+                assert!((a - b).abs() < 1e-12);
+            }
+        }
+        for (a, b) in low.iter().zip(fit.coefficients()) {
+            assert!((a - b).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn too_many_degrees() {
+        let pts = [(0.0, 1.0), (1.0, 3.0), (1.0, 2.0), (2.0, 0.0)];
+        let mut fit = fit_discrete_orthogonal(&pts, None, 2).unwrap();
+        // The parabola through (0, 1), (1, 2.5) and (2, 0).
+        assert!((fit.calc(1.0) - 2.5).abs() < 1e-14);
+        assert!((fit.rss() - 0.5).abs() < 1e-14);
+        assert_eq!(fit.raise_degree(), Err(FitError::RankDeficient { rank: 3, condition: f64::INFINITY }));
+        assert_eq!(fit.degree(), 2);
+    }
+}
@@ -0,0 +1,365 @@
+use std::error::Error;
+use std::fmt;
+
+use stats::{mad, median, normal_cdf, student_t_quantile};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OutlierError {
+    // NaN or infinite value with this index.
+    NotFinite { index: usize },
+}
+
+impl fmt::Display for OutlierError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            OutlierError::NotFinite { index } => write!(f, "value #{} is not finite", index),
+        }
+    }
+}
+
+impl Error for OutlierError {}
+
+// Outlier tests for a sample of values, usually the residuals of a fit.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Detector {
+    // |x - mean|/sd > threshold.
+    ZScore { threshold: f64 },
+    // Iglewicz and Hoaglin: 0.6745*|x - median|/MAD > threshold.
+    ModifiedZ { threshold: f64 },
+    // Moving window of 2*half_window + 1 values in input order:
+    // |x - median|/(1.4826*MAD) > threshold, both over the window.
+    Hampel { half_window: usize, threshold: f64 },
+    // Two-sided Grubbs test at level alpha, repeated on the remaining values
+    // while the most extreme one is significant.
+    Grubbs { alpha: f64 },
+    // Rosner's generalized ESD test for up to max_outliers outliers.
+    GeneralizedEsd { max_outliers: usize, alpha: f64 },
+    // Chauvenet's criterion: rejects x when n*P(|Z| >= |x - mean|/sd) < 1/2.
+    Chauvenet,
+}
+
+impl Detector {
+    pub fn z_score() -> Detector {
+        Detector::ZScore { threshold: 3.0 }
+    }
+
+    pub fn modified_z() -> Detector {
+        Detector::ModifiedZ { threshold: 3.5 }
+    }
+
+    pub fn hampel() -> Detector {
+        Detector::Hampel { half_window: 5, threshold: 3.0 }
+    }
+
+    pub fn grubbs() -> Detector {
+        Detector::Grubbs { alpha: 0.05 }
+    }
+
+    // Up to a tenth of the sample.
+    pub fn generalized_esd(count: usize) -> Detector {
+        Detector::GeneralizedEsd { max_outliers: count / 10, alpha: 0.05 }
+    }
+}
+
+impl fmt::Display for Detector {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Detector::ZScore { threshold } => write!(f, "z-score > {}", threshold),
+            Detector::ModifiedZ { threshold } => write!(f, "modified z-score > {}", threshold),
+            Detector::Hampel { half_window, threshold } => write!(f, "Hampel filter ({} each side) > {}", half_window, threshold),
+            Detector::Grubbs { alpha } => write!(f, "Grubbs (alpha {})", alpha),
+            Detector::GeneralizedEsd { max_outliers, alpha } => write!(f, "generalized ESD (<= {}, alpha {})", max_outliers, alpha),
+            Detector::Chauvenet => write!(f, "Chauvenet"),
+        }
+    }
+}
+
+// A flagged value. score is the test statistic of the detector: |z| for
+// ZScore and Chauvenet, the modified z-score, the Hampel ratio, and the
+// Grubbs or ESD statistic of the round in which the value was removed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Outlier {
+    pub index: usize,
+    pub score: f64,
+}
+
+// Flagged values in ascending index order.
+pub fn detect(values: &[f64], detector: Detector) -> Result<Vec<Outlier>, OutlierError> {
+    if let Some(index) = values.iter().position(|v| !v.is_finite()) {
+        return Err(OutlierError::NotFinite { index });
+    }
+    let all = (0..values.len()).collect::<Vec<_>>();
+    let mut res = match detector {
+        Detector::ZScore { threshold } => flag(z_scores(values), threshold),
+        Detector::ModifiedZ { threshold } => {
+            let m = median(values);
+            let s = mad(values) / 0.6745;
+            flag(values.iter().map(|&x| ratio(x - m, s)).collect(), threshold)
+        }
+        Detector::Hampel { half_window, threshold } => {
+            let scores = (0..values.len())
+                .map(|i| {
+                    let window = &values[i.saturating_sub(half_window)..(i + half_window + 1).min(values.len())];
+                    ratio(values[i] - median(window), 1.4826 * mad(window))
+                })
+                .collect();
+            flag(scores, threshold)
+        }
+        Detector::Grubbs { alpha } => {
+            let mut left = all;
+            let mut res = Vec::new();
+            while let Some((pos, g)) = most_extreme(values, &left) {
+                if g <= grubbs_critical(left.len(), alpha) {
+                    break;
+                }
+                res.push(Outlier { index: left.remove(pos), score: g });
+            }
+            res
+        }
+        Detector::GeneralizedEsd { max_outliers, alpha } => {
+            let mut left = all;
+            let mut candidates = Vec::new();
+            let mut count = 0;
+            for _ in 0..max_outliers {
+                let n = left.len();
+                let (pos, r) = match most_extreme(values, &left) {
+                    Some(extreme) => extreme,
+                    None => break,
+                };
+                candidates.push(Outlier { index: left.remove(pos), score: r });
+                if r > grubbs_critical(n, alpha) {
+                    count = candidates.len();
+                }
+            }
+            candidates.truncate(count);
+            candidates
+        }
+        Detector::Chauvenet => {
+            let n = values.len() as f64;
+            z_scores(values)
+                .into_iter()
+                .enumerate()
+                .filter(|&(_, z)| n * 2.0 * (1.0 - normal_cdf(z)) < 0.5)
+                .map(|(index, score)| Outlier { index, score })
+                .collect()
+        }
+    };
+
+    res.sort_by_key(|o| o.index);
+    Ok(res)
+}
+
+// d/s, infinite when the scale is zero and d is not.
+fn ratio(d: f64, s: f64) -> f64 {
+    if s > 0.0 {
+        d.abs() / s
+    } else if d == 0.0 {
+        0.0
+    } else {
+        f64::INFINITY
+    }
+}
+
+fn flag(scores: Vec<f64>, threshold: f64) -> Vec<Outlier> {
+    scores.into_iter()
+        .enumerate()
+        .filter(|&(_, s)| s > threshold)
+        .map(|(index, score)| Outlier { index, score })
+        .collect()
+}
+
+// Mean and sample standard deviation of values[i] for i in idx.
+fn mean_sd(values: &[f64], idx: &[usize]) -> (f64, f64) {
+    let n = idx.len() as f64;
+    let mean = idx.iter().map(|&i| values[i]).sum::<f64>() / n;
+    let var = idx.iter().map(|&i| (values[i] - mean).powi(2)).sum::<f64>() / (n - 1.0);
+    (mean, var.sqrt())
+}
+
+fn z_scores(values: &[f64]) -> Vec<f64> {
+    if values.len() < 2 {
+        return vec![0.0; values.len()];
+    }
+    let (mean, sd) = mean_sd(values, &(0..values.len()).collect::<Vec<_>>());
+    values.iter().map(|&x| ratio(x - mean, sd)).collect()
+}
+
+// Position in idx of the value furthest from the mean and its |z|; None with
+// fewer than three values or no spread.
+fn most_extreme(values: &[f64], idx: &[usize]) -> Option<(usize, f64)> {
+    if idx.len() < 3 {
+        return None;
+    }
+    let (mean, sd) = mean_sd(values, idx);
+    if sd == 0.0 || sd.is_nan() {
+        return None;
+    }
+    idx.iter()
+        .map(|&i| (values[i] - mean).abs() / sd)
+        .enumerate()
+        .fold(None, |best: Option<(usize, f64)>, (pos, g)| match best {
+            Some((_, b)) if b >= g => best,
+            _ => Some((pos, g)),
+        })
+}
+
+// Two-sided critical value of the Grubbs statistic for n values:
+// (n - 1)/sqrt(n) * sqrt(t^2/(n - 2 + t^2)), t the upper alpha/(2n) point of
+// Student's t with n - 2 degrees of freedom. It is also Rosner's lambda.
+fn grubbs_critical(n: usize, alpha: f64) -> f64 {
+    let n = n as f64;
+    let t = student_t_quantile(1.0 - alpha / (2.0 * n), n - 2.0);
+    (n - 1.0) / n.sqrt() * (t * t / (n - 2.0 + t * t)).sqrt()
+}
+
+// Confusion counts of flagged indices against the known outliers.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Evaluation {
+    pub true_positives: usize,
+    pub false_positives: usize,
+    pub false_negatives: usize,
+}
+
+impl Evaluation {
+    // Share of the flagged values that are outliers; 1 when nothing is flagged.
+    pub fn precision(&self) -> f64 {
+        let flagged = self.true_positives + self.false_positives;
+        if flagged == 0 { 1.0 } else { self.true_positives as f64 / flagged as f64 }
+    }
+
+    // Share of the outliers that are flagged; 1 when there are none.
+    pub fn recall(&self) -> f64 {
+        let actual = self.true_positives + self.false_negatives;
+        if actual == 0 { 1.0 } else { self.true_positives as f64 / actual as f64 }
+    }
+
+    // Counts of several runs added up.
+    pub fn merge(&self, other: &Evaluation) -> Evaluation {
+        Evaluation {
+            true_positives: self.true_positives + other.true_positives,
+            false_positives: self.false_positives + other.false_positives,
+            false_negatives: self.false_negatives + other.false_negatives,
+        }
+    }
+}
+
+pub fn evaluate(flagged: &[Outlier], truth: &[usize]) -> Evaluation {
+    let true_positives = flagged.iter().filter(|o| truth.contains(&o.index)).count();
+    Evaluation {
+        true_positives,
+        false_positives: flagged.len() - true_positives,
+        false_negatives: truth.len() - true_positives,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lsq::polynomial_basis;
+    use robust::{fit_robust, Loss};
+    use synthetic::{DataGenerator, Noise, OutlierCount, Outliers, Sign};
+
+    fn indices(flagged: &[Outlier]) -> Vec<usize> {
+        flagged.iter().map(|o| o.index).collect()
+    }
+
+    #[test]
+    fn small_samples() {
+        // With n values |z| <= (n - 1)/sqrt(n), so the z-score test needs
+        // more than ten of them to reach 3.
+        let values = [0.1, -0.3, 0.2, 0.0, 9.0, -0.1, 0.3, -0.2, 0.05, -0.15,
+                      0.12, -0.25, 0.18, 0.02, -0.05, -0.12, 0.28, -0.22, 0.07, -0.1];
+        for &detector in &[Detector::z_score(),
+                           Detector::modified_z(),
+                           Detector::hampel(),
+                           Detector::grubbs(),
+                           Detector::generalized_esd(30),
+                           Detector::Chauvenet] {
+            assert_eq!(indices(&detect(&values, detector).unwrap()), vec![4], "{}", detector);
+        }
+        assert!(detect(&[], Detector::grubbs()).unwrap().is_empty());
+        assert!(detect(&[1.0, 1.0, 1.0], Detector::modified_z()).unwrap().is_empty());
+        assert_eq!(indices(&detect(&[1.0, 1.0, 1.0, 5.0, 1.0], Detector::modified_z()).unwrap()), vec![3]);
+    }
+
+    #[test]
+    fn non_finite_values() {
+        for &detector in &[Detector::z_score(),
+                           Detector::modified_z(),
+                           Detector::hampel(),
+                           Detector::grubbs(),
+                           Detector::generalized_esd(30),
+                           Detector::Chauvenet] {
+            assert_eq!(detect(&[0.1, -0.2, f64::NAN, 0.3], detector), Err(OutlierError::NotFinite { index: 2 }));
+            assert_eq!(detect(&[f64::INFINITY, 0.0], detector), Err(OutlierError::NotFinite { index: 0 }));
+        }
+    }
+
+    #[test]
+    fn grubbs_statistic() {
+        // G = 2.838 for the 245.57 against the 5% critical value 2.290 of
+        // n = 10; without it nothing is significant.
+        let values = [199.31, 199.53, 200.19, 200.82, 201.92, 201.95, 202.18, 245.57, 202.24, 201.0];
+        let flagged = detect(&values, Detector::grubbs()).unwrap();
+        assert_eq!(indices(&flagged), vec![7]);
+        assert!((flagged[0].score - 2.8378).abs() < 1e-4);
+        assert!((grubbs_critical(10, 0.05) - 2.290).abs() < 1e-3);
+    }
+
+    #[test]
+    fn rosner_example() {
+        // Rosner's example from the NIST handbook, 54 values with 3 outliers.
+        let values = [-0.25, 0.68, 0.94, 1.15, 1.20, 1.26, 1.26, 1.34, 1.38, 1.43, 1.49, 1.49, 1.55, 1.56,
+                      1.58, 1.65, 1.69, 1.70, 1.76, 1.77, 1.81, 1.91, 1.94, 1.96, 1.99, 2.06, 2.09, 2.10,
+                      2.14, 2.15, 2.23, 2.24, 2.26, 2.35, 2.37, 2.40, 2.47, 2.54, 2.62, 2.64, 2.90, 2.92,
+                      2.92, 2.93, 3.21, 3.26, 3.30, 3.59, 3.68, 4.30, 4.64, 5.34, 5.42, 6.01];
+        let flagged = detect(&values, Detector::GeneralizedEsd { max_outliers: 10, alpha: 0.05 }).unwrap();
+        assert_eq!(indices(&flagged), vec![51, 52, 53]);
+        assert!((flagged[2].score - 3.118).abs() < 1e-3);
+    }
+
+    #[test]
+    fn evaluation_counts() {
+        let flagged = [Outlier { index: 1, score: 5.0 }, Outlier { index: 4, score: 4.0 }];
+        let eval = evaluate(&flagged, &[1, 2, 3]);
+        assert_eq!(eval, Evaluation { true_positives: 1, false_positives: 1, false_negatives: 2 });
+        assert_eq!(eval.precision(), 0.5);
+        assert!((eval.recall() - 1.0 / 3.0).abs() < 1e-15);
+        assert_eq!(Evaluation::default().precision(), 1.0);
+    }
+
+    // y = 1.5x + 1 with normal noise (sigma 3) on 100 points and 5 outliers
+    // of 20 to 30 sigma with random sign, all drawn from the seed. Returns the
+    // residuals of a robust line fit and the outlier indices.
+    fn synthetic_residuals(seed: usize) -> (Vec<f64>, Vec<usize>) {
+        let data = DataGenerator::new(|x| 1.5 * x + 1.0, seed)
+            .with_noise(Noise::Gaussian { sigma: 3.0 })
+            .with_outliers(Outliers { count: OutlierCount::Count(5), magnitude: (60.0, 90.0), sign: Sign::Random })
+            .generate(&::linspace(0.0, 99.0, 100))
+            .unwrap();
+        let fit = fit_robust(&polynomial_basis(1), &data.points(), None, Loss::tukey()).unwrap();
+        (fit.residuals().to_vec(), data.outliers().to_vec())
+    }
+
+    #[test]
+    fn precision_and_recall() {
+        // The counts are pooled over 100 seeds, enough for the rates to
+        // settle. The Hampel filter estimates the scale from eleven values
+        // only, so it also flags about 2.4% of the ordinary noise: its
+        // precision comes to 0.68 over 200 seeds, between 0.64 and 0.73 for
+        // blocks of 20, and 0.6 leaves room for that spread.
+        let runs = (0..100).map(synthetic_residuals).collect::<Vec<_>>();
+        for &(detector, min_precision, min_recall) in &[(Detector::z_score(), 1.0, 0.95),
+                                                        (Detector::modified_z(), 0.95, 1.0),
+                                                        (Detector::hampel(), 0.6, 1.0),
+                                                        (Detector::grubbs(), 0.95, 1.0),
+                                                        (Detector::generalized_esd(100), 0.95, 1.0),
+                                                        (Detector::Chauvenet, 1.0, 0.95)] {
+            let total = runs.iter().fold(Evaluation::default(), |acc, (residuals, truth)| {
+                acc.merge(&evaluate(&detect(residuals, detector).unwrap(), truth))
+            });
+            assert!(total.precision() >= min_precision && total.recall() >= min_recall,
+                    "{}: precision {}, recall {}", detector, total.precision(), total.recall());
+        }
+    }
+}
@@ -0,0 +1,245 @@
+use rand::{Rng, SeedableRng, StdRng};
+
+use lsq::{FitError, LeastSquares, LinearFit};
+
+// RANSAC: fits the model exactly through random minimal samples of
+// basis_len points, keeps the sample that agrees with the most points
+// (|residual| <= threshold, ties going to the smaller sum of squared inlier
+// residuals) and refits it by least squares over its inliers, repeating while
+// the consensus of the refit grows. The sampling is driven by a seeded
+// generator, so a given seed always gives the same fit.
+#[derive(Clone)]
+pub struct Ransac<'a> {
+    model: LeastSquares<'a>,
+    threshold: f64,
+    iterations: usize,
+    seed: usize,
+}
+
+const REFINE_STEPS: usize = 10;
+
+impl<'a> Ransac<'a> {
+    // 1000 iterations, seed 0.
+    pub fn new(model: LeastSquares<'a>, threshold: f64) -> Ransac<'a> {
+        Ransac {
+            model,
+            threshold,
+            iterations: 1000,
+            seed: 0,
+        }
+    }
+
+    pub fn with_iterations(mut self, iterations: usize) -> Ransac<'a> {
+        self.iterations = iterations;
+        self
+    }
+
+    pub fn with_seed(mut self, seed: usize) -> Ransac<'a> {
+        self.seed = seed;
+        self
+    }
+
+    pub fn fit(&self, pts: &[(f64, f64)]) -> Result<RansacFit<'a>, FitError> {
+        if !(self.threshold.is_finite() && self.threshold >= 0.0) {
+            return Err(FitError::InvalidThreshold { threshold: self.threshold });
+        }
+        if self.iterations == 0 {
+            return Err(FitError::NoIterations);
+        }
+        let k = self.model.basis_len();
+        if k == 0 {
+            return Err(FitError::NoBasis);
+        }
+        if pts.len() < k {
+            return Err(FitError::TooFewPoints { needed: k, found: pts.len() });
+        }
+        if let Some(index) = pts.iter().position(|&(x, y)| !x.is_finite() || !y.is_finite()) {
+            return Err(FitError::NotFinite { index });
+        }
+
+        let mut rng = StdRng::from_seed(&[self.seed][..]);
+        // (inlier count, inlier rss, inliers)
+        let mut best: Option<(usize, f64, Vec<usize>)> = None;
+        let mut last_err = None;
+        let mut order = (0..pts.len()).collect::<Vec<_>>();
+        for _ in 0..self.iterations {
+            // Partial Fisher-Yates shuffle: order[..k] is a uniform sample
+            // without repetition.
+            for j in 0..k {
+                let pick = rng.gen_range(j, pts.len());
+                order.swap(j, pick);
+            }
+            let sample = order[..k].iter().map(|&i| pts[i]).collect::<Vec<_>>();
+            let candidate = match self.model.fit(&sample, None) {
+                Ok(fit) => fit,
+                Err(err) => {
+                    // Degenerate sample, e.g. repeated abscissae.
+                    last_err = Some(err);
+                    continue;
+                }
+            };
+            let (inliers, rss) = self.consensus(&candidate, pts);
+            let better = match best {
+                None => true,
+                Some((count, best_rss, _)) => inliers.len() > count || (inliers.len() == count && rss < best_rss),
+            };
+            if better {
+                best = Some((inliers.len(), rss, inliers));
+            }
+        }
+
+        let mut inliers = match best {
+            Some((_, _, inliers)) => inliers,
+            None => return Err(last_err.unwrap_or(FitError::NoIterations)),
+        };
+        let mut fit = self.refit(pts, &inliers)?;
+        for _ in 0..REFINE_STEPS {
+            let (next, _) = self.consensus(&fit, pts);
+            if next == inliers || next.len() < inliers.len() {
+                break;
+            }
+            inliers = next;
+            fit = self.refit(pts, &inliers)?;
+        }
+        // When the refinement stops on a shrinking set or runs out of steps,
+        // the last refit does not reproduce the set it came from.
+        let inliers = self.consensus(&fit, pts).0;
+
+        let residuals = pts.iter().map(|&(x, y)| y - fit.calc(x)).collect();
+        Ok(RansacFit { fit, inliers, residuals })
+    }
+
+    fn consensus(&self, fit: &LinearFit, pts: &[(f64, f64)]) -> (Vec<usize>, f64) {
+        let mut inliers = Vec::new();
+        let mut rss = 0.0;
+        for (i, &(x, y)) in pts.iter().enumerate() {
+            let r = y - fit.calc(x);
+            if r.abs() <= self.threshold {
+                inliers.push(i);
+                rss += r * r;
+            }
+        }
+        (inliers, rss)
+    }
+
+    fn refit(&self, pts: &[(f64, f64)], inliers: &[usize]) -> Result<LinearFit<'a>, FitError> {
+        self.model.fit(&inliers.iter().map(|&i| pts[i]).collect::<Vec<_>>(), None)
+    }
+}
+
+#[derive(Clone)]
+pub struct RansacFit<'a> {
+    fit: LinearFit<'a>,
+    inliers: Vec<usize>,
+    residuals: Vec<f64>,
+}
+
+impl<'a> RansacFit<'a> {
+    // Least-squares fit over the last consensus set of the refinement, which
+    // can differ slightly from inliers() when the refinement did not settle.
+    pub fn fit(&self) -> &LinearFit<'a> {
+        &self.fit
+    }
+
+    pub fn coefficients(&self) -> &[f64] {
+        self.fit.coefficients()
+    }
+
+    pub fn calc(&self, x: f64) -> f64 {
+        self.fit.calc(x)
+    }
+
+    // Indices of the points within the threshold of the final fit, ascending.
+    pub fn inliers(&self) -> &[usize] {
+        &self.inliers
+    }
+
+    // y[i] - fit(x[i]) for all input points.
+    pub fn residuals(&self) -> &[f64] {
+        &self.residuals
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lsq::polynomial_basis;
+    use tests::noise;
+
+    #[test]
+    fn line_with_outliers() {
+        let outliers = [3, 17, 18, 40, 41, 42, 77];
+        let pts = (0..100)
+            .map(|i| {
+                let x = i as f64 * 0.5;
+                let y = 2.0 - 0.7 * x + noise(i);
+                (x, if outliers.contains(&i) { y - 30.0 } else { y })
+            })
+            .collect::<Vec<_>>();
+
+        let ransac = Ransac::new(polynomial_basis(1), 1.0).with_iterations(200).with_seed(7);
+        let fit = ransac.fit(&pts).unwrap();
+        assert!((fit.coefficients()[0] - 2.0).abs() < 0.2);
+        assert!((fit.coefficients()[1] + 0.7).abs() < 0.01);
+        let expected = (0..100).filter(|i| !outliers.contains(i)).collect::<Vec<_>>();
+        assert_eq!(fit.inliers(), &expected[..]);
+        assert!((fit.residuals()[40] + 30.0).abs() < 1.0);
+
+        // The same seed gives the same fit.
+        let again = ransac.fit(&pts).unwrap();
+        assert_eq!(again.coefficients(), fit.coefficients());
+    }
+
+    #[test]
+    fn inliers_match_final_fit() {
+        // The consensus of y = 0 takes in the points at +0.99 and the one at
+        // -0.99; the refit over them rises by about 0.15 and leaves the
+        // latter beyond the threshold, so the consensus set shrinks.
+        let pts = (0..26)
+            .map(|i| {
+                let y = match i {
+                    3 | 8 | 13 | 18 | 23 => 0.99,
+                    11 => -0.99,
+                    _ => 0.0,
+                };
+                (i as f64, y)
+            })
+            .collect::<Vec<_>>();
+        let fit = Ransac::new(polynomial_basis(0), 1.0).with_iterations(50).fit(&pts).unwrap();
+        assert!(!fit.inliers().contains(&11));
+        assert_eq!(fit.inliers().len(), 25);
+        for (i, r) in fit.residuals().iter().enumerate() {
+            assert_eq!(r.abs() <= 1.0, fit.inliers().contains(&i), "point {}: residual {}", i, r);
+        }
+    }
+
+    #[test]
+    fn parabola_model() {
+        let pts = (0..60)
+            .map(|i| {
+                let x = i as f64 / 10.0;
+                let y = x * x - 3.0 * x + 0.1 * noise(i);
+                (x, if i % 10 == 5 { y + 5.0 } else { y })
+            })
+            .collect::<Vec<_>>();
+        let fit = Ransac::new(polynomial_basis(2), 0.2).with_seed(1).fit(&pts).unwrap();
+        assert_eq!(fit.inliers().len(), 54);
+        assert!(fit.inliers().iter().all(|i| i % 10 != 5));
+        assert!((fit.calc(2.0) + 2.0).abs() < 0.05);
+    }
+
+    #[test]
+    fn degenerate_input() {
+        assert_eq!(Ransac::new(polynomial_basis(1), 1.0).fit(&[(0.0, 1.0)]).err(),
+                   Some(FitError::TooFewPoints { needed: 2, found: 1 }));
+        let vertical = [(1.0, 0.0), (1.0, 1.0), (1.0, 2.0)];
+        assert!(matches!(Ransac::new(polynomial_basis(1), 1.0).with_iterations(10).fit(&vertical),
+                         Err(FitError::RankDeficient { .. })));
+
+        let pts = [(0.0, 1.0), (1.0, 2.0), (2.0, 3.0)];
+        for &threshold in &[-1.0, f64::NAN, f64::INFINITY] {
+            assert!(matches!(Ransac::new(polynomial_basis(1), threshold).fit(&pts), Err(FitError::InvalidThreshold { .. })));
+        }
+        assert_eq!(Ransac::new(polynomial_basis(1), 1.0).with_iterations(0).fit(&pts).err(), Some(FitError::NoIterations));
+    }
+}
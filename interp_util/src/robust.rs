@@ -0,0 +1,239 @@
+use lsq::{FitError, LeastSquares, LinearFit};
+use stats::mad;
+
+// Loss of an M-estimator, given by its weight function w(u) = psi(u)/u of the
+// scaled residual u = r/s. The default tuning constants give 95% efficiency
+// for normal errors.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Loss {
+    // Quadratic for |u| <= k, linear beyond: w = min(1, k/|u|).
+    Huber { k: f64 },
+    // Tukey's bisquare: w = (1 - (u/c)^2)^2 for |u| < c, zero beyond, so gross
+    // outliers are dropped altogether.
+    Tukey { c: f64 },
+    // w = 1/(1 + (u/c)^2).
+    Cauchy { c: f64 },
+}
+
+impl Loss {
+    pub fn huber() -> Loss {
+        Loss::Huber { k: 1.345 }
+    }
+
+    pub fn tukey() -> Loss {
+        Loss::Tukey { c: 4.685 }
+    }
+
+    pub fn cauchy() -> Loss {
+        Loss::Cauchy { c: 2.385 }
+    }
+
+    pub fn weight(&self, u: f64) -> f64 {
+        match *self {
+            Loss::Huber { k } => if u.abs() <= k { 1.0 } else { k / u.abs() },
+            Loss::Tukey { c } => if u.abs() < c { (1.0 - (u / c).powi(2)).powi(2) } else { 0.0 },
+            Loss::Cauchy { c } => 1.0 / (1.0 + (u / c).powi(2)),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct RobustFit<'a> {
+    fit: LinearFit<'a>,
+    weights: Vec<f64>,
+    residuals: Vec<f64>,
+    scale: f64,
+    iterations: usize,
+    converged: bool,
+}
+
+impl<'a> RobustFit<'a> {
+    // Weighted least-squares fit of the last accepted iteration, point i
+    // weighted by prior[i]*weights()[i]; rejected points keep a tiny
+    // positive weight instead of zero.
+    pub fn fit(&self) -> &LinearFit<'a> {
+        &self.fit
+    }
+
+    pub fn coefficients(&self) -> &[f64] {
+        self.fit.coefficients()
+    }
+
+    pub fn calc(&self, x: f64) -> f64 {
+        self.fit.calc(x)
+    }
+
+    // Final robust weights in [0, 1], in the order of the input points and
+    // without the prior weights.
+    pub fn weights(&self) -> &[f64] {
+        &self.weights
+    }
+
+    // y[i] - fit(x[i]) for all input points.
+    pub fn residuals(&self) -> &[f64] {
+        &self.residuals
+    }
+
+    // Robust residual scale, 1.4826 * MAD of the residuals.
+    pub fn scale(&self) -> f64 {
+        self.scale
+    }
+
+    pub fn iterations(&self) -> usize {
+        self.iterations
+    }
+
+    pub fn converged(&self) -> bool {
+        self.converged
+    }
+
+    // Indices of the points whose robust weight is below min_weight; with
+    // Loss::Tukey, min_weight = f64::MIN_POSITIVE gives the rejected points.
+    pub fn outliers(&self, min_weight: f64) -> Vec<usize> {
+        (0..self.weights.len()).filter(|&i| self.weights[i] < min_weight).collect()
+    }
+}
+
+const MAX_ITERATIONS: usize = 100;
+
+// M-estimation by iteratively reweighted least squares: starting from the
+// (prior weighted) least-squares fit, the residuals are scaled by 1.4826*MAD,
+// every point gets the weight prior[i]*w(r[i]/s), and the problem is refitted
+// until the coefficients settle. Tukey's loss is not convex, so it starts from
+// the converged Huber fit.
+pub fn fit_robust<'a>(lsq: &LeastSquares<'a>, pts: &[(f64, f64)], weights: Option<&[f64]>, loss: Loss) -> Result<RobustFit<'a>, FitError> {
+    let prior = |i: usize| weights.map_or(1.0, |w| w[i]);
+    let mut fit = lsq.fit(pts, weights)?;
+    let mut robust = vec![1.0; pts.len()];
+    let mut scale = 0.0;
+    let mut iterations = 0;
+    let mut converged = false;
+
+    let stages = match loss {
+        Loss::Tukey { .. } => vec![Loss::huber(), loss],
+        _ => vec![loss],
+    };
+    for stage in stages {
+        converged = false;
+        for _ in 0..MAX_ITERATIONS {
+            let residuals = pts.iter().map(|&(x, y)| y - fit.calc(x)).collect::<Vec<_>>();
+            scale = 1.4826 * mad(&residuals);
+            if scale == 0.0 {
+                // Most of the points are fitted exactly.
+                converged = true;
+                break;
+            }
+            iterations += 1;
+            let trial = residuals.iter().map(|r| stage.weight(r / scale)).collect::<Vec<_>>();
+
+            // Rejected points keep a tiny weight, so the problem stays
+            // well posed however many of them there are. If the points left
+            // with a real weight cannot determine the model any more, the
+            // previous fit stands together with the weights it came from.
+            let w = (0..pts.len()).map(|i| (prior(i) * trial[i]).max(f64::MIN_POSITIVE)).collect::<Vec<_>>();
+            let next = match lsq.fit(pts, Some(&w)) {
+                Ok(next) => next,
+                Err(_) => {
+                    converged = false;
+                    break;
+                }
+            };
+            robust = trial;
+
+            let size = next.coefficients().iter().fold(0.0f64, |acc, c| acc.max(c.abs()));
+            let change = next.coefficients()
+                .iter()
+                .zip(fit.coefficients())
+                .fold(0.0f64, |acc, (a, b)| acc.max((a - b).abs()));
+            fit = next;
+            if change <= 1e-10 * (1.0 + size) {
+                converged = true;
+                break;
+            }
+        }
+    }
+
+    let residuals = pts.iter().map(|&(x, y)| y - fit.calc(x)).collect();
+    Ok(RobustFit { fit, weights: robust, residuals, scale, iterations, converged })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lsq::polynomial_basis;
+    use tests::{contaminated_line, noise};
+
+    #[test]
+    fn weight_functions() {
+        assert_eq!(Loss::huber().weight(1.0), 1.0);
+        assert!((Loss::huber().weight(-2.69) - 0.5).abs() < 1e-12);
+        assert_eq!(Loss::tukey().weight(0.0), 1.0);
+        assert_eq!(Loss::tukey().weight(5.0), 0.0);
+        assert!((Loss::cauchy().weight(2.385) - 0.5).abs() < 1e-12);
+    }
+
+    #[test]
+    fn losses_recover_line() {
+        let (pts, outliers) = contaminated_line();
+        let ols = polynomial_basis(1).fit(&pts, None).unwrap();
+        assert!((ols.coefficients()[0] - 1.0).abs() > 1.0);
+
+        for &loss in &[Loss::huber(), Loss::tukey(), Loss::cauchy()] {
+            let robust = fit_robust(&polynomial_basis(1), &pts, None, loss).unwrap();
+            assert!(robust.converged());
+            let c = robust.coefficients();
+            assert!((c[0] - 1.0).abs() < 0.5, "{:?}: {:?}", loss, c);
+            assert!((c[1] - 1.5).abs() < 0.01, "{:?}: {:?}", loss, c);
+            for &i in &outliers {
+                assert!(robust.weights()[i] < 0.1, "{:?}: weight {}", loss, robust.weights()[i]);
+                assert!((robust.residuals()[i] - 80.0).abs() < 2.0);
+            }
+        }
+
+        let tukey = fit_robust(&polynomial_basis(1), &pts, None, Loss::tukey()).unwrap();
+        assert_eq!(tukey.outliers(f64::MIN_POSITIVE), outliers);
+        assert!(tukey.scale() > 0.3 && tukey.scale() < 1.0);
+    }
+
+    #[test]
+    fn clean_data_matches_least_squares() {
+        let pts = (0..50).map(|i| (i as f64 * 0.1, (i as f64 * 0.1).sin() + 0.1 * noise(i))).collect::<Vec<_>>();
+        let ols = polynomial_basis(3).fit(&pts, None).unwrap();
+        let huber = fit_robust(&polynomial_basis(3), &pts, None, Loss::Huber { k: 10.0 }).unwrap();
+        for (a, b) in ols.coefficients().iter().zip(huber.coefficients()) {
+            assert!((a - b).abs() < 1e-12);
+        }
+        assert!(huber.weights().iter().all(|&w| w == 1.0));
+    }
+
+    #[test]
+    fn most_points_rejected() {
+        // A narrow bisquare rejects nearly all of the scattered majority;
+        // the fit has to go on with the rest instead of failing. Narrower
+        // still, the first bisquare refit cannot be solved, and the Huber
+        // fit it started from stands with its own weights.
+        let pts = (0..20)
+            .map(|i| (i as f64, 1.5 * i as f64 + if i % 3 == 0 { 0.0 } else { 40.0 * noise(i) }))
+            .collect::<Vec<_>>();
+        for &(c, converged, rejected) in &[(0.5, true, 12), (0.1, false, 0)] {
+            let robust = fit_robust(&polynomial_basis(1), &pts, None, Loss::Tukey { c }).unwrap();
+            assert_eq!(robust.converged(), converged, "c = {}", c);
+            assert_eq!(robust.outliers(f64::MIN_POSITIVE).len(), rejected, "c = {}", c);
+            assert!(robust.coefficients().iter().all(|c| c.is_finite()));
+            assert_eq!(robust.residuals().len(), pts.len());
+            // The reported weights are the ones the returned fit was made with.
+            let w = robust.weights().iter().map(|w| w.max(f64::MIN_POSITIVE)).collect::<Vec<_>>();
+            assert_eq!(polynomial_basis(1).fit(&pts, Some(&w)).unwrap().coefficients(), robust.coefficients());
+        }
+    }
+
+    #[test]
+    fn prior_weights() {
+        let (pts, _) = contaminated_line();
+        assert_eq!(fit_robust(&polynomial_basis(1), &pts, Some(&[1.0; 3]), Loss::huber()).err(),
+                   Some(FitError::InvalidWeights));
+        let w = (0..pts.len()).map(|i| if i < 50 { 1.0 } else { 4.0 }).collect::<Vec<_>>();
+        let robust = fit_robust(&polynomial_basis(1), &pts, Some(&w), Loss::tukey()).unwrap();
+        assert!((robust.coefficients()[1] - 1.5).abs() < 0.01);
+    }
+}
@@ -0,0 +1,335 @@
+use linalg::{LinalgError, SymmetricBanded};
+use spline::{check_points, check_weights, from_moments, CubicSpline, SplineError};
+
+// How the smoothing spline picks its regularization weight.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Smoothing {
+    // Fixed finite lambda >= 0, anything else fails with InvalidLambda; zero
+    // gives the natural interpolating spline.
+    Lambda(f64),
+    // Lambda minimizing the generalized cross-validation score.
+    Gcv,
+}
+
+// Cubic smoothing spline: the natural cubic spline g minimizing
+// sum(w[i]*(y[i] - g(x[i]))^2) + lambda*integral(g''^2).
+#[derive(Debug, Clone)]
+pub struct SmoothingSpline {
+    spline: CubicSpline,
+    lambda: f64,
+    dof: f64,
+    rss: f64,
+    gcv: f64,
+}
+
+impl SmoothingSpline {
+    pub fn spline(&self) -> &CubicSpline {
+        &self.spline
+    }
+
+    pub fn into_spline(self) -> CubicSpline {
+        self.spline
+    }
+
+    pub fn calc(&self, x: f64) -> f64 {
+        self.spline.calc(x)
+    }
+
+    pub fn lambda(&self) -> f64 {
+        self.lambda
+    }
+
+    // Equivalent degrees of freedom, the trace of the influence matrix.
+    pub fn dof(&self) -> f64 {
+        self.dof
+    }
+
+    // Weighted residual sum of squares.
+    pub fn rss(&self) -> f64 {
+        self.rss
+    }
+
+    // Generalized cross-validation score n*rss / (n - dof)^2.
+    pub fn gcv(&self) -> f64 {
+        self.gcv
+    }
+}
+
+// Reinsch's formulation: with the second derivatives gamma at the interior
+// knots, (R + lambda*Q^T*W^-1*Q)*gamma = Q^T*y and g = y - lambda*W^-1*Q*gamma,
+// where Q is the (n x n-2) second difference matrix and R is tridiagonal.
+struct Reinsch {
+    t: Vec<f64>,
+    y: Vec<f64>,
+    w: Vec<f64>,
+    h: Vec<f64>,
+    r: SymmetricBanded,
+    qtwq: SymmetricBanded,
+    qty: Vec<f64>,
+}
+
+struct ReinschFit {
+    g: Vec<f64>,
+    gamma: Vec<f64>,
+    dof: f64,
+    rss: f64,
+}
+
+impl Reinsch {
+    fn new(t: Vec<f64>, y: Vec<f64>, w: Vec<f64>) -> Reinsch {
+        let n = t.len();
+        let m = n - 2;
+        let h = t.windows(2).map(|w| w[1] - w[0]).collect::<Vec<_>>();
+
+        let mut r = SymmetricBanded::zeros(m, 1);
+        let mut qtwq = SymmetricBanded::zeros(m, 2);
+        let mut qty = Vec::with_capacity(m);
+        for j in 0..m {
+            r.add(j, j, (h[j] + h[j + 1]) / 3.0);
+            if j + 1 < m {
+                r.add(j, j + 1, h[j + 1] / 6.0);
+            }
+            qty.push((y[j + 2] - y[j + 1]) / h[j + 1] - (y[j + 1] - y[j]) / h[j]);
+        }
+        for (i, &wi) in w.iter().enumerate() {
+            let cols = Reinsch::q_row(&h, i);
+            for &(a, qa) in &cols {
+                for &(b, qb) in &cols {
+                    if a <= b {
+                        qtwq.add(a, b, qa * qb / wi);
+                    }
+                }
+            }
+        }
+
+        Reinsch { t, y, w, h, r, qtwq, qty }
+    }
+
+    // Nonzero entries (column, value) of the i-th row of Q.
+    fn q_row(h: &[f64], i: usize) -> Vec<(usize, f64)> {
+        let m = h.len() - 1;
+        let mut res = Vec::with_capacity(3);
+        if i >= 2 {
+            res.push((i - 2, 1.0 / h[i - 1]));
+        }
+        if i >= 1 && i - 1 < m {
+            res.push((i - 1, -1.0 / h[i - 1] - 1.0 / h[i]));
+        }
+        if i < m {
+            res.push((i, 1.0 / h[i]));
+        }
+        res
+    }
+
+    fn fit(&self, lambda: f64) -> Result<ReinschFit, LinalgError> {
+        let n = self.t.len();
+        let m = n - 2;
+        let mut a = SymmetricBanded::zeros(m, 2);
+        for i in 0..m {
+            for j in i..(i + 3).min(m) {
+                a.add(i, j, self.r.get(i, j) + lambda * self.qtwq.get(i, j));
+            }
+        }
+        let ldl = a.ldl()?;
+        let gamma = ldl.solve(&self.qty)?;
+
+        let g = (0..n)
+            .map(|i| {
+                let q_gamma = Reinsch::q_row(&self.h, i).iter().map(|&(j, q)| q * gamma[j]).sum::<f64>();
+                self.y[i] - lambda * q_gamma / self.w[i]
+            })
+            .collect::<Vec<_>>();
+        let rss = (0..n).map(|i| self.w[i] * (self.y[i] - g[i]).powi(2)).sum();
+
+        // tr(A) = n - lambda*tr(M^-1 * Q^T*W^-1*Q), only the band of M^-1 is needed.
+        let inv = ldl.inverse_band();
+        let mut tr = 0.0;
+        for i in 0..m {
+            tr += inv.get(i, i) * self.qtwq.get(i, i);
+            for j in i + 1..(i + 3).min(m) {
+                tr += 2.0 * inv.get(i, j) * self.qtwq.get(i, j);
+            }
+        }
+
+        Ok(ReinschFit { g, gamma, dof: n as f64 - lambda * tr, rss })
+    }
+
+    fn gcv(&self, fit: &ReinschFit) -> f64 {
+        let n = self.t.len() as f64;
+        n * fit.rss / (n - fit.dof).powi(2)
+    }
+
+    // Grid search over log(lambda) around the scale where the two terms of
+    // R + lambda*Q^T*W^-1*Q balance, refined by golden-section search.
+    fn gcv_lambda(&self) -> Result<f64, LinalgError> {
+        let m = self.qty.len();
+        let (tr_r, tr_q) = (0..m).fold((0.0, 0.0), |(a, b), i| (a + self.r.get(i, i), b + self.qtwq.get(i, i)));
+        let log_scale = (tr_r / tr_q).log10();
+        let score = |log_lambda: f64| -> Result<f64, LinalgError> {
+            let fit = self.fit(10f64.powf(log_lambda))?;
+            Ok(self.gcv(&fit))
+        };
+
+        let step = 0.25;
+        let mut best = (log_scale - 6.0, f64::INFINITY);
+        let mut log_lambda = log_scale - 6.0;
+        while log_lambda <= log_scale + 6.0 {
+            let v = score(log_lambda)?;
+            if v < best.1 {
+                best = (log_lambda, v);
+            }
+            log_lambda += step;
+        }
+
+        let ratio = 0.5 * (5f64.sqrt() - 1.0);
+        let (mut a, mut b) = (best.0 - step, best.0 + step);
+        let mut c = b - ratio * (b - a);
+        let mut d = a + ratio * (b - a);
+        let (mut fc, mut fd) = (score(c)?, score(d)?);
+        for _ in 0..40 {
+            if fc < fd {
+                b = d;
+                d = c;
+                fd = fc;
+                c = b - ratio * (b - a);
+                fc = score(c)?;
+            } else {
+                a = c;
+                c = d;
+                fc = fd;
+                d = a + ratio * (b - a);
+                fd = score(d)?;
+            }
+        }
+        let refined = 0.5 * (a + b);
+
+        Ok(10f64.powf(if score(refined)? < best.1 { refined } else { best.0 }))
+    }
+}
+
+// Points may come in any order; weights default to 1. Needs at least three
+// points.
+pub fn create_smoothing_spline(pts: &[(f64, f64)], weights: Option<&[f64]>, smoothing: Smoothing) -> Result<SmoothingSpline, SplineError> {
+    check_weights(weights, pts.len())?;
+    check_points(pts, 3)?;
+
+    let mut data = pts.iter()
+        .enumerate()
+        .map(|(i, &(x, y))| (x, y, weights.map_or(1.0, |w| w[i])))
+        .collect::<Vec<_>>();
+    data.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+    let t = data.iter().map(|p| p.0).collect::<Vec<_>>();
+    let y = data.iter().map(|p| p.1).collect::<Vec<_>>();
+    let w = data.iter().map(|p| p.2).collect::<Vec<_>>();
+
+    let reinsch = Reinsch::new(t, y, w);
+    let lambda = match smoothing {
+        Smoothing::Lambda(lambda) if !(lambda.is_finite() && lambda >= 0.0) => {
+            return Err(SplineError::InvalidLambda { lambda });
+        }
+        Smoothing::Lambda(lambda) => lambda,
+        Smoothing::Gcv => reinsch.gcv_lambda()?,
+    };
+    let fit = reinsch.fit(lambda)?;
+    let gcv = reinsch.gcv(&fit);
+
+    let mut z = Vec::with_capacity(fit.gamma.len() + 2);
+    z.push(0.0);
+    z.extend_from_slice(&fit.gamma);
+    z.push(0.0);
+
+    Ok(SmoothingSpline {
+        spline: from_moments(reinsch.t, &fit.g, &z),
+        lambda,
+        dof: fit.dof,
+        rss: fit.rss,
+        gcv,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use spline::create_cubic_spline_natural;
+    use tests::noise;
+
+    #[test]
+    fn zero_lambda_interpolates() {
+        let pts = [(0.0, 1.0), (0.5, 2.0), (1.3, 0.5), (2.0, 1.5), (3.1, 3.0)];
+        let smooth = create_smoothing_spline(&pts, None, Smoothing::Lambda(0.0)).unwrap();
+        let natural = create_cubic_spline_natural(&pts).unwrap();
+        for &x in &[0.0, 0.25, 0.9, 1.7, 2.5, 3.1] {
+            assert!((smooth.calc(x) - natural.calc(x)).abs() < 1e-12);
+        }
+        assert!((smooth.dof() - pts.len() as f64).abs() < 1e-9);
+        assert!(smooth.rss() < 1e-20);
+    }
+
+    #[test]
+    fn large_lambda_gives_regression_line() {
+        let pts = (0..20).map(|i| (i as f64 * 0.5, 1.0 + 0.3 * i as f64 * 0.5 + noise(i))).collect::<Vec<_>>();
+        let smooth = create_smoothing_spline(&pts, None, Smoothing::Lambda(1e9)).unwrap();
+
+        let n = pts.len() as f64;
+        let mx = pts.iter().map(|p| p.0).sum::<f64>() / n;
+        let my = pts.iter().map(|p| p.1).sum::<f64>() / n;
+        let slope = pts.iter().map(|p| (p.0 - mx) * (p.1 - my)).sum::<f64>() /
+                    pts.iter().map(|p| (p.0 - mx).powi(2)).sum::<f64>();
+        for &x in &[0.0, 3.3, 9.5] {
+            assert!((smooth.calc(x) - (my + slope * (x - mx))).abs() < 1e-5);
+        }
+        assert!((smooth.dof() - 2.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn gcv_smooths_noisy_data() {
+        let f = |x: f64| (2.0 * x).sin();
+        let pts = (0..80).map(|i| i as f64 * 0.05).enumerate()
+            .map(|(i, x)| (x, f(x) + 0.3 * noise(i)))
+            .collect::<Vec<_>>();
+        let smooth = create_smoothing_spline(&pts, None, Smoothing::Gcv).unwrap();
+
+        assert!(smooth.lambda() > 0.0);
+        assert!(smooth.dof() > 2.0 && smooth.dof() < 20.0);
+        let err = pts.iter().map(|p| (smooth.calc(p.0) - f(p.0)).powi(2)).sum::<f64>() / pts.len() as f64;
+        let noise_var = pts.iter().map(|p| (p.1 - f(p.0)).powi(2)).sum::<f64>() / pts.len() as f64;
+        assert!(err < 0.25 * noise_var, "error {} vs noise {}", err, noise_var);
+
+        // No other lambda on a coarse grid does better than the chosen one.
+        for k in -4..5 {
+            let lambda = smooth.lambda() * 10f64.powi(k);
+            let other = create_smoothing_spline(&pts, None, Smoothing::Lambda(lambda)).unwrap();
+            assert!(other.gcv() >= smooth.gcv() * (1.0 - 1e-9));
+        }
+    }
+
+    #[test]
+    fn weights() {
+        let pts = (0..15).map(|i| (i as f64, noise(i))).collect::<Vec<_>>();
+        let mut w = vec![1.0; pts.len()];
+        w[7] = 1e8;
+        let smooth = create_smoothing_spline(&pts, Some(&w), Smoothing::Lambda(10.0)).unwrap();
+        assert!((smooth.calc(7.0) - pts[7].1).abs() < 1e-5);
+
+        assert_eq!(create_smoothing_spline(&pts, Some(&w[1..]), Smoothing::Gcv).unwrap_err(),
+                   SplineError::InvalidWeights);
+        w[3] = 0.0;
+        assert_eq!(create_smoothing_spline(&pts, Some(&w), Smoothing::Gcv).unwrap_err(),
+                   SplineError::InvalidWeights);
+        assert_eq!(create_smoothing_spline(&pts[..2], None, Smoothing::Gcv).unwrap_err(),
+                   SplineError::TooFewPoints { needed: 3, found: 2 });
+    }
+
+    #[test]
+    fn invalid_lambda() {
+        let pts = (0..15).map(|i| (i as f64, noise(i))).collect::<Vec<_>>();
+        for &lambda in &[-1e-3, f64::INFINITY] {
+            assert_eq!(create_smoothing_spline(&pts, None, Smoothing::Lambda(lambda)).unwrap_err(),
+                       SplineError::InvalidLambda { lambda });
+        }
+        match create_smoothing_spline(&pts, None, Smoothing::Lambda(f64::NAN)) {
+            Err(SplineError::InvalidLambda { lambda }) => assert!(lambda.is_nan()),
+            other => panic!("{:?}", other.map(|s| s.lambda())),
+        }
+    }
+}
@@ -0,0 +1,876 @@
+use std::error::Error;
+use std::fmt;
+
+use bspline::KnotError;
+use linalg::{solve_cyclic_tridiagonal, solve_tridiagonal, LinalgError};
+
+// One piece of a cubic spline on [t_begin, t_end], stored in the
+// second-derivative (moment) form:
+// S(x) = c_begin*(x - t_begin)^3 + c_end*(t_end - x)^3 + l_begin*(x - t_begin) + l_end*(t_end - x)
+#[derive(Debug, Clone)]
+pub struct CubicSection {
+    c_begin: f64,
+    c_end: f64,
+
+    l_begin: f64,
+    l_end: f64,
+
+    t_begin: f64,
+    t_end: f64,
+}
+
+impl CubicSection {
+    // Builds the section from the values and second derivatives at both ends.
+    pub fn from_moments(t_begin: f64, t_end: f64, y_begin: f64, y_end: f64, z_begin: f64, z_end: f64) -> CubicSection {
+        let h = t_end - t_begin;
+        CubicSection {
+            c_begin: z_end / (6.0 * h),
+            c_end: z_begin / (6.0 * h),
+            l_begin: y_end / h - z_end * h / 6.0,
+            l_end: y_begin / h - h * z_begin / 6.0,
+            t_begin,
+            t_end,
+        }
+    }
+
+    // Builds the section from the values and first derivatives at both ends.
+    pub fn from_hermite(t_begin: f64, t_end: f64, y_begin: f64, y_end: f64, der_begin: f64, der_end: f64) -> CubicSection {
+        let h = t_end - t_begin;
+        let b = (y_end - y_begin) / h;
+        let z_begin = (6.0 * b - 4.0 * der_begin - 2.0 * der_end) / h;
+        let z_end = (2.0 * der_begin + 4.0 * der_end - 6.0 * b) / h;
+        CubicSection::from_moments(t_begin, t_end, y_begin, y_end, z_begin, z_end)
+    }
+
+    pub fn t_begin(&self) -> f64 {
+        self.t_begin
+    }
+
+    pub fn t_end(&self) -> f64 {
+        self.t_end
+    }
+
+    pub fn calc(&self, x: f64) -> f64 {
+        self.c_begin * (x - self.t_begin).powi(3) + self.c_end * (self.t_end - x).powi(3) +
+        self.l_begin * (x - self.t_begin) + self.l_end * (self.t_end - x)
+    }
+
+    pub fn calc_der(&self, x: f64) -> f64 {
+        3.0 * self.c_begin * (x - self.t_begin).powi(2) -
+        3.0 * self.c_end * (self.t_end - x).powi(2) + self.l_begin - self.l_end
+    }
+
+    pub fn calc_der2(&self, x: f64) -> f64 {
+        6.0 * self.c_begin * (x - self.t_begin) + 6.0 * self.c_end * (self.t_end - x)
+    }
+
+    fn calc_order(&self, order: usize, x: f64) -> f64 {
+        match order {
+            0 => self.calc(x),
+            1 => self.calc_der(x),
+            _ => self.calc_der2(x),
+        }
+    }
+}
+
+// What a spline returns outside of its knot range.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Extrapolation {
+    // try_calc* return None, calc* panic.
+    Error,
+    // The value at the nearest end, with zero derivatives.
+    Clamp,
+    // Straight line continuing the end value and slope.
+    Linear,
+    // The cubic of the first or last section.
+    Cubic,
+    // NaN for the value and the derivatives.
+    Nan,
+}
+
+#[derive(Debug, Clone)]
+pub struct CubicSpline {
+    sections: Vec<CubicSection>,
+    section_bounds: Vec<f64>,
+    extrapolation: Extrapolation,
+}
+
+impl CubicSpline {
+    // Splines are built with Extrapolation::Cubic.
+    pub fn with_extrapolation(mut self, extrapolation: Extrapolation) -> CubicSpline {
+        self.extrapolation = extrapolation;
+        self
+    }
+
+    pub fn extrapolation(&self) -> Extrapolation {
+        self.extrapolation
+    }
+
+    pub fn calc(&self, x: f64) -> f64 {
+        self.eval(x, 0)
+    }
+
+    pub fn calc_der(&self, x: f64) -> f64 {
+        self.eval(x, 1)
+    }
+
+    pub fn calc_der2(&self, x: f64) -> f64 {
+        self.eval(x, 2)
+    }
+
+    pub fn try_calc(&self, x: f64) -> Option<f64> {
+        self.eval_section(self.find_section_index(x), x, 0)
+    }
+
+    pub fn try_calc_der(&self, x: f64) -> Option<f64> {
+        self.eval_section(self.find_section_index(x), x, 1)
+    }
+
+    pub fn try_calc_der2(&self, x: f64) -> Option<f64> {
+        self.eval_section(self.find_section_index(x), x, 2)
+    }
+
+    pub fn sections(&self) -> &[CubicSection] {
+        &self.sections
+    }
+
+    pub fn knots(&self) -> &[f64] {
+        &self.section_bounds
+    }
+
+    // Batch versions of calc, calc_der and calc_der2 for queries sorted in
+    // ascending order: the current section is kept between points, so a sweep
+    // over the whole spline costs O(n + m). Out-of-order queries fall back to
+    // the binary search.
+    pub fn calc_sorted(&self, xs: &[f64]) -> Vec<f64> {
+        self.eval_sorted(xs, 0)
+    }
+
+    pub fn calc_der_sorted(&self, xs: &[f64]) -> Vec<f64> {
+        self.eval_sorted(xs, 1)
+    }
+
+    pub fn calc_der2_sorted(&self, xs: &[f64]) -> Vec<f64> {
+        self.eval_sorted(xs, 2)
+    }
+
+    fn eval(&self, x: f64, order: usize) -> f64 {
+        self.eval_section(self.find_section_index(x), x, order)
+            .unwrap_or_else(|| panic!("{} is outside of the spline range", x))
+    }
+
+    fn eval_sorted(&self, xs: &[f64], order: usize) -> Vec<f64> {
+        let last = self.sections.len() - 1;
+        let mut cursor = 0;
+        xs.iter()
+            .map(|&x| {
+                if x < self.section_bounds[cursor] {
+                    cursor = self.find_section_index(x);
+                }
+                while cursor < last && x >= self.section_bounds[cursor + 1] {
+                    cursor += 1;
+                }
+                self.eval_section(cursor, x, order)
+                    .unwrap_or_else(|| panic!("{} is outside of the spline range", x))
+            })
+            .collect()
+    }
+
+    fn eval_section(&self, idx: usize, x: f64, order: usize) -> Option<f64> {
+        let section = &self.sections[idx];
+        let first = self.section_bounds[0];
+        let last = self.section_bounds[self.section_bounds.len() - 1];
+        if !(x < first || x > last) {
+            return Some(section.calc_order(order, x));
+        }
+
+        let bound = if x < first { first } else { last };
+        match self.extrapolation {
+            Extrapolation::Error => None,
+            Extrapolation::Nan => Some(f64::NAN),
+            Extrapolation::Cubic => Some(section.calc_order(order, x)),
+            Extrapolation::Clamp => Some(if order == 0 { section.calc(bound) } else { 0.0 }),
+            Extrapolation::Linear => {
+                Some(match order {
+                    0 => section.calc(bound) + section.calc_der(bound) * (x - bound),
+                    1 => section.calc_der(bound),
+                    _ => 0.0,
+                })
+            }
+        }
+    }
+
+    // Index of the section whose right bound is the first knot greater than x,
+    // points outside the knots go to the first or the last section.
+    fn find_section_index(&self, x: f64) -> usize {
+        let idx = self.section_bounds[1..].partition_point(|&t| t <= x);
+        idx.min(self.sections.len() - 1)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SplineError {
+    TooFewPoints { needed: usize, found: usize },
+    // NaN or infinite coordinate in the input point with this index.
+    NotFinite { index: usize },
+    DuplicateAbscissa { x: f64 },
+    // A periodic spline needs the last ordinate to repeat the first one.
+    PeriodicMismatch { first: f64, last: f64 },
+    // Weights do not match the points in number, or one of them is not
+    // positive and finite.
+    InvalidWeights,
+    // Smoothing weight that is negative or not finite.
+    InvalidLambda { lambda: f64 },
+    Knots(KnotError),
+    Linalg(LinalgError),
+}
+
+impl fmt::Display for SplineError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SplineError::TooFewPoints { needed, found } => {
+                write!(f, "spline needs at least {} points, got {}", needed, found)
+            }
+            SplineError::NotFinite { index } => write!(f, "point #{} is not finite", index),
+            SplineError::DuplicateAbscissa { x } => write!(f, "duplicate abscissa {}", x),
+            SplineError::PeriodicMismatch { first, last } => {
+                write!(f, "periodic spline ends at {} but starts at {}", last, first)
+            }
+            SplineError::InvalidWeights => write!(f, "invalid weights"),
+            SplineError::InvalidLambda { lambda } => write!(f, "invalid smoothing weight {}", lambda),
+            SplineError::Knots(ref err) => write!(f, "knots: {}", err),
+            SplineError::Linalg(ref err) => write!(f, "spline system: {}", err),
+        }
+    }
+}
+
+impl Error for SplineError {}
+
+impl From<LinalgError> for SplineError {
+    fn from(err: LinalgError) -> SplineError {
+        SplineError::Linalg(err)
+    }
+}
+
+impl From<KnotError> for SplineError {
+    fn from(err: KnotError) -> SplineError {
+        SplineError::Knots(err)
+    }
+}
+
+pub(crate) fn check_weights(weights: Option<&[f64]>, count: usize) -> Result<(), SplineError> {
+    match weights {
+        Some(w) if w.len() != count || w.iter().any(|&w| !(w.is_finite() && w > 0.0)) => Err(SplineError::InvalidWeights),
+        _ => Ok(()),
+    }
+}
+
+// Rejects non-finite input and repeated abscissae, and returns the points
+// sorted by abscissa.
+pub(crate) fn check_points(pts: &[(f64, f64)], needed: usize) -> Result<Vec<(f64, f64)>, SplineError> {
+    if pts.len() < needed {
+        return Err(SplineError::TooFewPoints { needed, found: pts.len() });
+    }
+    if let Some(index) = pts.iter().position(|&(x, y)| !x.is_finite() || !y.is_finite()) {
+        return Err(SplineError::NotFinite { index });
+    }
+
+    let mut sorted = pts.to_vec();
+    sorted.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+    if let Some(w) = sorted.windows(2).find(|w| w[0].0 == w[1].0) {
+        return Err(SplineError::DuplicateAbscissa { x: w[0].0 });
+    }
+
+    Ok(sorted)
+}
+
+// Knot abscissae, ordinates, interval lengths and secant slopes of the data.
+fn split_points(pts: &[(f64, f64)]) -> (Vec<f64>, Vec<f64>, Vec<f64>, Vec<f64>) {
+    let t = pts.iter().map(|&(t, _)| t).collect::<Vec<_>>();
+    let y = pts.iter().map(|&(_, y)| y).collect::<Vec<_>>();
+    let h = t.windows(2).map(|w| w[1] - w[0]).collect::<Vec<_>>();
+    let b = y.windows(2).zip(h.iter()).map(|(w, h)| (w[1] - w[0]) / h).collect::<Vec<_>>();
+
+    (t, y, h, b)
+}
+
+// End conditions of a cubic spline.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Boundary {
+    // Zero second derivative at both ends.
+    Natural,
+    // Prescribed first derivatives f'(a) and f'(b).
+    Clamped { der_begin: f64, der_end: f64 },
+    // Clamped, with the end slopes taken from the first and last secants.
+    SecantClamped,
+    // Continuous third derivative at the second and the second to last knots.
+    // Three points give the interpolating parabola.
+    NotAKnot,
+    // S, S' and S'' match at both ends; the last ordinate must repeat the first.
+    Periodic,
+}
+
+// Tridiagonal system for the moments z[i] = S''(t[i]) with the interior rows
+// h[i-1]*z[i-1] + 2*(h[i-1] + h[i])*z[i] + h[i]*z[i+1] = 6*(b[i] - b[i-1])
+// filled in; the first and last rows are left for the boundary condition.
+fn interior_system(h: &[f64], b: &[f64]) -> (Vec<f64>, Vec<f64>, Vec<f64>, Vec<f64>) {
+    let n = h.len() + 1;
+    let mut lower = vec![0.0; n - 1];
+    let mut diag = vec![0.0; n];
+    let mut upper = vec![0.0; n - 1];
+    let mut rhs = vec![0.0; n];
+
+    for i in 1..n - 1 {
+        lower[i - 1] = h[i - 1];
+        diag[i] = 2.0 * (h[i - 1] + h[i]);
+        upper[i] = h[i];
+        rhs[i] = 6.0 * (b[i] - b[i - 1]);
+    }
+
+    (lower, diag, upper, rhs)
+}
+
+fn solve_moments(h: &[f64], b: &[f64], boundary: Boundary) -> Result<Vec<f64>, LinalgError> {
+    let (der_begin, der_end) = match boundary {
+        Boundary::Natural => return solve_moments_natural(h, b),
+        Boundary::Clamped { der_begin, der_end } => (der_begin, der_end),
+        Boundary::SecantClamped => (b[0], b[b.len() - 1]),
+        Boundary::NotAKnot => return solve_moments_not_a_knot(h, b),
+        Boundary::Periodic => return solve_moments_periodic(h, b),
+    };
+
+    let n = h.len() + 1;
+    let (mut lower, mut diag, mut upper, mut rhs) = interior_system(h, b);
+
+    // S'(t0) = b0 - h0*(2*z0 + z1)/6 and S'(tn) = b[n-2] + h[n-2]*(z[n-2] + 2*z[n-1])/6
+    diag[0] = 2.0 * h[0];
+    upper[0] = h[0];
+    rhs[0] = 6.0 * (b[0] - der_begin);
+    lower[n - 2] = h[n - 2];
+    diag[n - 1] = 2.0 * h[n - 2];
+    rhs[n - 1] = 6.0 * (der_end - b[n - 2]);
+
+    solve_tridiagonal(&lower, &diag, &upper, &rhs)
+}
+
+fn solve_moments_natural(h: &[f64], b: &[f64]) -> Result<Vec<f64>, LinalgError> {
+    let n = h.len() + 1;
+    let (lower, mut diag, upper, rhs) = interior_system(h, b);
+    diag[0] = 1.0;
+    diag[n - 1] = 1.0;
+
+    solve_tridiagonal(&lower, &diag, &upper, &rhs)
+}
+
+// The not-a-knot conditions (z1 - z0)/h0 = (z2 - z1)/h1 (and the same at the
+// other end) are used to eliminate z0 and zn, which keeps the system for
+// z1..z[n-2] tridiagonal.
+fn solve_moments_not_a_knot(h: &[f64], b: &[f64]) -> Result<Vec<f64>, LinalgError> {
+    let n = h.len() + 1;
+    match n {
+        2 => return Ok(vec![0.0; 2]),
+        3 => return Ok(vec![2.0 * (b[1] - b[0]) / (h[0] + h[1]); 3]),
+        _ => (),
+    }
+
+    let (lower, diag, upper, rhs) = interior_system(h, b);
+    let mut lower = lower[1..n - 2].to_vec();
+    let mut diag = diag[1..n - 1].to_vec();
+    let mut upper = upper[1..n - 2].to_vec();
+    let rhs = &rhs[1..n - 1];
+    let m = diag.len();
+
+    let (h0, h1) = (h[0], h[1]);
+    diag[0] = (h0 + h1) * (h0 + 2.0 * h1) / h1;
+    upper[0] = (h1 * h1 - h0 * h0) / h1;
+
+    let (hl, hr) = (h[n - 3], h[n - 2]);
+    diag[m - 1] = (hl + hr) * (2.0 * hl + hr) / hl;
+    lower[m - 2] = (hl * hl - hr * hr) / hl;
+
+    let res = solve_tridiagonal(&lower, &diag, &upper, rhs)?;
+    let mut z = Vec::with_capacity(n);
+    z.push(((h0 + h1) * res[0] - h0 * res[1]) / h1);
+    z.extend_from_slice(&res);
+    z.push(((hl + hr) * res[m - 1] - hr * res[m - 2]) / hl);
+
+    Ok(z)
+}
+
+// The moments z0..z[n-2] form a cyclic system, z[n-1] repeats z0.
+fn solve_moments_periodic(h: &[f64], b: &[f64]) -> Result<Vec<f64>, LinalgError> {
+    let m = h.len();
+    match m {
+        1 => return Ok(vec![0.0; 2]),
+        2 => {
+            // [2s s; s 2s] z = r with s = h0 + h1
+            let s = h[0] + h[1];
+            let (r0, r1) = (6.0 * (b[0] - b[1]), 6.0 * (b[1] - b[0]));
+            let z0 = (2.0 * r0 - r1) / (3.0 * s);
+            return Ok(vec![z0, (2.0 * r1 - r0) / (3.0 * s), z0]);
+        }
+        _ => (),
+    }
+
+    let mut lower = Vec::with_capacity(m - 1);
+    let mut diag = Vec::with_capacity(m);
+    let mut upper = Vec::with_capacity(m - 1);
+    let mut rhs = Vec::with_capacity(m);
+
+    for i in 0..m {
+        let prev = (i + m - 1) % m;
+        diag.push(2.0 * (h[prev] + h[i]));
+        rhs.push(6.0 * (b[i] - b[prev]));
+        if i > 0 {
+            lower.push(h[i - 1]);
+        }
+        if i < m - 1 {
+            upper.push(h[i]);
+        }
+    }
+
+    let mut z = solve_cyclic_tridiagonal(&lower, &diag, &upper, &rhs, h[m - 1], h[m - 1])?;
+    let z0 = z[0];
+    z.push(z0);
+
+    Ok(z)
+}
+
+pub(crate) fn from_moments(t: Vec<f64>, y: &[f64], z: &[f64]) -> CubicSpline {
+    let sections = (0..t.len() - 1)
+        .map(|i| CubicSection::from_moments(t[i], t[i + 1], y[i], y[i + 1], z[i], z[i + 1]))
+        .collect();
+
+    CubicSpline {
+        sections,
+        section_bounds: t,
+        extrapolation: Extrapolation::Cubic,
+    }
+}
+
+// Piecewise cubic Hermite spline with the slopes m[i] at the knots.
+pub(crate) fn from_slopes(t: Vec<f64>, y: &[f64], m: &[f64]) -> CubicSpline {
+    let sections = (0..t.len() - 1)
+        .map(|i| CubicSection::from_hermite(t[i], t[i + 1], y[i], y[i + 1], m[i], m[i + 1]))
+        .collect();
+
+    CubicSpline {
+        sections,
+        section_bounds: t,
+        extrapolation: Extrapolation::Cubic,
+    }
+}
+
+// Spline from consecutive sections, each starting where the previous ends.
+pub(crate) fn from_sections(sections: Vec<CubicSection>) -> CubicSpline {
+    let mut section_bounds = sections.iter().map(|s| s.t_begin).collect::<Vec<_>>();
+    section_bounds.push(sections[sections.len() - 1].t_end);
+
+    CubicSpline {
+        sections,
+        section_bounds,
+        extrapolation: Extrapolation::Cubic,
+    }
+}
+
+// The points may come in any order. Two points give a straight line for every
+// boundary condition except Clamped.
+pub fn create_cubic_spline(pts: &[(f64, f64)], boundary: Boundary) -> Result<CubicSpline, SplineError> {
+    let pts = check_points(pts, 2)?;
+    let (t, y, h, b) = split_points(&pts);
+    if boundary == Boundary::Periodic {
+        let (first, last) = (y[0], y[y.len() - 1]);
+        if (first - last).abs() > 1e-12 * first.abs().max(last.abs()).max(1.0) {
+            return Err(SplineError::PeriodicMismatch { first, last });
+        }
+    }
+    let z = solve_moments(&h, &b, boundary)?;
+
+    Ok(from_moments(t, &y, &z))
+}
+
+pub fn create_cubic_spline_natural(pts: &[(f64, f64)]) -> Result<CubicSpline, SplineError> {
+    create_cubic_spline(pts, Boundary::Natural)
+}
+
+pub fn create_cubic_spline_clamped(pts: &[(f64, f64)], der_begin: f64, der_end: f64) -> Result<CubicSpline, SplineError> {
+    create_cubic_spline(pts, Boundary::Clamped { der_begin, der_end })
+}
+
+// Monotone piecewise cubic (PCHIP): Fritsch-Carlson slopes, the weighted
+// harmonic mean of the neighbouring secants, or zero at local extrema. The
+// spline is monotone wherever the data is and does not overshoot.
+pub fn create_pchip_spline(pts: &[(f64, f64)]) -> Result<CubicSpline, SplineError> {
+    let pts = check_points(pts, 2)?;
+    let (t, y, h, b) = split_points(&pts);
+    let n = t.len();
+    if n == 2 {
+        return Ok(from_slopes(t, &y, &[b[0], b[0]]));
+    }
+
+    let mut m = vec![0.0; n];
+    for k in 1..n - 1 {
+        if b[k - 1] * b[k] > 0.0 {
+            let w1 = 2.0 * h[k] + h[k - 1];
+            let w2 = h[k] + 2.0 * h[k - 1];
+            m[k] = (w1 + w2) / (w1 / b[k - 1] + w2 / b[k]);
+        }
+    }
+    m[0] = pchip_end_slope(h[0], h[1], b[0], b[1]);
+    m[n - 1] = pchip_end_slope(h[n - 2], h[n - 3], b[n - 2], b[n - 3]);
+
+    Ok(from_slopes(t, &y, &m))
+}
+
+// Shape-preserving three-point estimate of the slope at an end knot; h0 and b0
+// belong to the end interval, h1 and b1 to its neighbour.
+fn pchip_end_slope(h0: f64, h1: f64, b0: f64, b1: f64) -> f64 {
+    let m = ((2.0 * h0 + h1) * b0 - h0 * b1) / (h0 + h1);
+    if m.signum() != b0.signum() || b0 == 0.0 {
+        0.0
+    } else if b0.signum() != b1.signum() && m.abs() > 3.0 * b0.abs() {
+        3.0 * b0
+    } else {
+        m
+    }
+}
+
+// Akima spline: the slope at a knot weights the two neighbouring secants by the
+// change of the secants on the far side, so a single outlier only bends the
+// spline locally. The secants are extended by two linear extrapolations at
+// each end.
+pub fn create_akima_spline(pts: &[(f64, f64)]) -> Result<CubicSpline, SplineError> {
+    let pts = check_points(pts, 2)?;
+    let (t, y, _, b) = split_points(&pts);
+    let n = t.len();
+    if n == 2 {
+        return Ok(from_slopes(t, &y, &[b[0], b[0]]));
+    }
+
+    // d[k + 2] is the secant of the interval k, k = -2..n
+    let mut d = Vec::with_capacity(n + 3);
+    d.push(3.0 * b[0] - 2.0 * b[1]);
+    d.push(2.0 * b[0] - b[1]);
+    d.extend_from_slice(&b);
+    d.push(2.0 * b[n - 2] - b[n - 3]);
+    d.push(3.0 * b[n - 2] - 2.0 * b[n - 3]);
+
+    let m = (0..n)
+        .map(|i| {
+            let w1 = (d[i + 3] - d[i + 2]).abs();
+            let w2 = (d[i + 1] - d[i]).abs();
+            if w1 + w2 == 0.0 {
+                0.5 * (d[i + 1] + d[i + 2])
+            } else {
+                (w1 * d[i + 1] + w2 * d[i + 2]) / (w1 + w2)
+            }
+        })
+        .collect::<Vec<_>>();
+
+    Ok(from_slopes(t, &y, &m))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_points() -> Vec<(f64, f64)> {
+        [-2.0f64, -1.3, -0.4, 0.0, 0.7, 1.5, 2.2, 3.0]
+            .iter()
+            .map(|&x| (x, (x * 1.3).sin() + 0.2 * x * x))
+            .collect()
+    }
+
+    fn check_interpolates_and_smooth(spline: &CubicSpline, pts: &[(f64, f64)]) {
+        for &(x, y) in pts {
+            assert!((spline.calc(x) - y).abs() < 1e-12, "S({}) = {}, expected {}", x, spline.calc(x), y);
+        }
+        for pair in spline.sections.windows(2) {
+            let (left, right) = (&pair[0], &pair[1]);
+            let t = left.t_end;
+            assert!((left.calc(t) - right.calc(t)).abs() < 1e-12);
+            assert!((left.calc_der(t) - right.calc_der(t)).abs() < 1e-10);
+            assert!((left.calc_der2(t) - right.calc_der2(t)).abs() < 1e-10);
+        }
+    }
+
+    #[test]
+    fn natural_spline() {
+        let pts = sample_points();
+        let spline = create_cubic_spline_natural(&pts).unwrap();
+        check_interpolates_and_smooth(&spline, &pts);
+        assert!(spline.calc_der2(pts[0].0).abs() < 1e-12);
+        assert!(spline.calc_der2(pts[pts.len() - 1].0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn clamped_spline() {
+        let pts = sample_points();
+        let spline = create_cubic_spline_clamped(&pts, 0.5, -1.5).unwrap();
+        check_interpolates_and_smooth(&spline, &pts);
+        assert!((spline.calc_der(pts[0].0) - 0.5).abs() < 1e-12);
+        assert!((spline.calc_der(pts[pts.len() - 1].0) + 1.5).abs() < 1e-12);
+    }
+
+    #[test]
+    fn clamped_spline_reproduces_cubic() {
+        let f = |x: f64| x * x * x - 2.0 * x * x + 0.5;
+        let df = |x: f64| 3.0 * x * x - 4.0 * x;
+        let pts = [-1.0, -0.2, 0.3, 1.1, 2.0].iter().map(|&x| (x, f(x))).collect::<Vec<_>>();
+        let spline = create_cubic_spline_clamped(&pts, df(-1.0), df(2.0)).unwrap();
+        for &x in &[-0.9, -0.5, 0.0, 0.7, 1.6, 1.95] {
+            assert!((spline.calc(x) - f(x)).abs() < 1e-12);
+            assert!((spline.calc_der(x) - df(x)).abs() < 1e-11);
+        }
+    }
+
+    #[test]
+    fn secant_clamped_spline() {
+        let pts = sample_points();
+        let spline = create_cubic_spline(&pts, Boundary::SecantClamped).unwrap();
+        check_interpolates_and_smooth(&spline, &pts);
+        let n = pts.len();
+        let first_secant = (pts[1].1 - pts[0].1) / (pts[1].0 - pts[0].0);
+        let last_secant = (pts[n - 1].1 - pts[n - 2].1) / (pts[n - 1].0 - pts[n - 2].0);
+        assert!((spline.calc_der(pts[0].0) - first_secant).abs() < 1e-12);
+        assert!((spline.calc_der(pts[n - 1].0) - last_secant).abs() < 1e-12);
+    }
+
+    #[test]
+    fn not_a_knot_reproduces_cubic() {
+        let f = |x: f64| 0.5 * x * x * x + x * x - 3.0 * x + 2.0;
+        let df = |x: f64| 1.5 * x * x + 2.0 * x - 3.0;
+        let pts = [-2.0, -1.5, -0.2, 0.4, 1.0, 2.5].iter().map(|&x| (x, f(x))).collect::<Vec<_>>();
+        let spline = create_cubic_spline(&pts, Boundary::NotAKnot).unwrap();
+        check_interpolates_and_smooth(&spline, &pts);
+        for &x in &[-1.9, -1.0, 0.0, 0.9, 2.2] {
+            assert!((spline.calc(x) - f(x)).abs() < 1e-11);
+            assert!((spline.calc_der(x) - df(x)).abs() < 1e-10);
+        }
+    }
+
+    #[test]
+    fn not_a_knot_four_points() {
+        let f = |x: f64| x * x * x - x;
+        let pts = [0.0, 0.3, 1.0, 1.2].iter().map(|&x| (x, f(x))).collect::<Vec<_>>();
+        let spline = create_cubic_spline(&pts, Boundary::NotAKnot).unwrap();
+        for &x in &[0.1, 0.5, 1.1] {
+            assert!((spline.calc(x) - f(x)).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn periodic_spline() {
+        use std::f64::consts::PI;
+
+        let n = 33;
+        let pts = (0..n)
+            .map(|i| 2.0 * PI * i as f64 / (n - 1) as f64)
+            .map(|x| (x, x.sin() + 0.5 * (2.0 * x).cos()))
+            .collect::<Vec<_>>();
+        let spline = create_cubic_spline(&pts, Boundary::Periodic).unwrap();
+        check_interpolates_and_smooth(&spline, &pts);
+
+        let (a, b) = (0.0, 2.0 * PI);
+        assert!((spline.calc_der(a) - spline.calc_der(b)).abs() < 1e-12);
+        assert!((spline.calc_der2(a) - spline.calc_der2(b)).abs() < 1e-12);
+        for i in 0..100 {
+            let x = 2.0 * PI * (i as f64 + 0.5) / 100.0;
+            let exact = x.sin() + 0.5 * (2.0 * x).cos();
+            let exact_der = x.cos() - (2.0 * x).sin();
+            assert!((spline.calc(x) - exact).abs() < 1e-4);
+            assert!((spline.calc_der(x) - exact_der).abs() < 5e-3);
+        }
+    }
+
+    #[test]
+    fn section_lookup() {
+        let pts = sample_points();
+        let spline = create_cubic_spline_natural(&pts).unwrap();
+        assert_eq!(spline.find_section_index(-5.0), 0);
+        assert_eq!(spline.find_section_index(-2.0), 0);
+        assert_eq!(spline.find_section_index(-1.3), 1);
+        assert_eq!(spline.find_section_index(-1.0), 1);
+        assert_eq!(spline.find_section_index(2.9), 6);
+        assert_eq!(spline.find_section_index(3.0), 6);
+        assert_eq!(spline.find_section_index(7.0), 6);
+    }
+
+    #[test]
+    fn sorted_evaluation() {
+        let pts = sample_points();
+        let spline = create_cubic_spline(&pts, Boundary::NotAKnot).unwrap();
+        let mut xs = ::linspace(-2.5, 3.5, 97);
+        xs.extend_from_slice(&[0.1, -1.8, 2.5]);
+
+        let ys = spline.calc_sorted(&xs);
+        let ders = spline.calc_der_sorted(&xs);
+        let ders2 = spline.calc_der2_sorted(&xs);
+        for (i, &x) in xs.iter().enumerate() {
+            assert_eq!(ys[i], spline.calc(x));
+            assert_eq!(ders[i], spline.calc_der(x));
+            assert_eq!(ders2[i], spline.calc_der2(x));
+        }
+    }
+
+    #[test]
+    fn extrapolation() {
+        let pts = sample_points();
+        let spline = create_cubic_spline_natural(&pts).unwrap();
+        let (a, b) = (pts[0].0, pts[pts.len() - 1].0);
+        let (ya, yb) = (pts[0].1, pts[pts.len() - 1].1);
+        let (da, db) = (spline.calc_der(a), spline.calc_der(b));
+        let cubic_below = spline.sections[0].calc(a - 1.0);
+
+        assert_eq!(spline.calc(a - 1.0), cubic_below);
+
+        let spline = spline.with_extrapolation(Extrapolation::Error);
+        assert_eq!(spline.try_calc(a - 1.0), None);
+        assert_eq!(spline.try_calc_der(b + 0.1), None);
+        assert!(spline.try_calc(a).is_some() && spline.try_calc(b).is_some());
+
+        let spline = spline.with_extrapolation(Extrapolation::Clamp);
+        assert_eq!(spline.calc(a - 1.0), ya);
+        assert_eq!(spline.calc(b + 2.0), yb);
+        assert_eq!(spline.calc_der(b + 2.0), 0.0);
+
+        let spline = spline.with_extrapolation(Extrapolation::Linear);
+        assert!((spline.calc(a - 1.0) - (ya - da)).abs() < 1e-12);
+        assert!((spline.calc(b + 2.0) - (yb + 2.0 * db)).abs() < 1e-12);
+        assert_eq!(spline.calc_der(b + 2.0), db);
+        assert_eq!(spline.calc_der2(a - 1.0), 0.0);
+        assert_eq!(spline.calc_sorted(&[a - 1.0, b + 2.0]), vec![spline.calc(a - 1.0), spline.calc(b + 2.0)]);
+
+        let spline = spline.with_extrapolation(Extrapolation::Nan);
+        assert!(spline.calc(b + 1e-9).is_nan());
+        assert!(!spline.calc(b).is_nan());
+    }
+
+    #[test]
+    #[should_panic]
+    fn extrapolation_error_panics() {
+        let spline = create_cubic_spline_natural(&sample_points()).unwrap().with_extrapolation(Extrapolation::Error);
+        spline.calc(10.0);
+    }
+
+    #[test]
+    fn invalid_input() {
+        assert_eq!(create_cubic_spline_natural(&[(1.0, 2.0)]).unwrap_err(),
+                   SplineError::TooFewPoints { needed: 2, found: 1 });
+        assert_eq!(create_cubic_spline_natural(&[(0.0, 1.0), (1.0, f64::NAN), (2.0, 0.0)]).unwrap_err(),
+                   SplineError::NotFinite { index: 1 });
+        assert_eq!(create_cubic_spline_natural(&[(0.0, 1.0), (1.0, 0.0), (0.0, 2.0)]).unwrap_err(),
+                   SplineError::DuplicateAbscissa { x: 0.0 });
+        assert_eq!(create_cubic_spline(&[(0.0, 1.0), (1.0, 0.0), (2.0, 2.0)], Boundary::Periodic).unwrap_err(),
+                   SplineError::PeriodicMismatch { first: 1.0, last: 2.0 });
+    }
+
+    #[test]
+    fn unsorted_input() {
+        let pts = sample_points();
+        let mut shuffled = pts.clone();
+        shuffled.reverse();
+        shuffled.swap(1, 4);
+        let spline = create_cubic_spline_natural(&pts).unwrap();
+        let shuffled_spline = create_cubic_spline_natural(&shuffled).unwrap();
+        for &x in &[-1.7, 0.3, 2.6] {
+            assert_eq!(spline.calc(x), shuffled_spline.calc(x));
+        }
+    }
+
+    #[test]
+    fn degenerate_sizes() {
+        let line = [(1.0, 1.0), (3.0, 2.0)];
+        for &boundary in &[Boundary::Natural, Boundary::SecantClamped, Boundary::NotAKnot] {
+            let spline = create_cubic_spline(&line, boundary).unwrap();
+            assert!((spline.calc(2.0) - 1.5).abs() < 1e-12);
+            assert!((spline.calc_der(1.2) - 0.5).abs() < 1e-12);
+        }
+        let hermite = create_cubic_spline_clamped(&line, 0.0, 0.0).unwrap();
+        assert!((hermite.calc(2.0) - 1.5).abs() < 1e-12);
+        assert!(hermite.calc_der(1.0).abs() < 1e-12);
+
+        let parabola = |x: f64| 2.0 * x * x - x + 1.0;
+        let pts = [(-1.0, parabola(-1.0)), (0.5, parabola(0.5)), (2.0, parabola(2.0))];
+        let spline = create_cubic_spline(&pts, Boundary::NotAKnot).unwrap();
+        for &x in &[-0.7, 0.0, 1.3] {
+            assert!((spline.calc(x) - parabola(x)).abs() < 1e-12);
+        }
+
+        let natural = create_cubic_spline_natural(&pts).unwrap();
+        check_interpolates_and_smooth(&natural, &pts);
+
+        let pts = [(0.0, 1.0), (1.0, -1.0), (3.0, 1.0)];
+        let spline = create_cubic_spline(&pts, Boundary::Periodic).unwrap();
+        check_interpolates_and_smooth(&spline, &pts);
+        assert!((spline.calc_der(0.0) - spline.calc_der(3.0)).abs() < 1e-12);
+        assert!((spline.calc_der2(0.0) - spline.calc_der2(3.0)).abs() < 1e-12);
+    }
+
+    #[test]
+    fn pchip_is_monotone() {
+        let pts = [(0.0, 0.0), (1.0, 0.1), (2.0, 0.15), (2.5, 3.0), (3.0, 3.1), (5.0, 3.2), (6.0, 3.2)];
+        let spline = create_pchip_spline(&pts).unwrap();
+        for &(x, y) in &pts {
+            assert!((spline.calc(x) - y).abs() < 1e-12);
+        }
+        for pair in spline.sections.windows(2) {
+            let t = pair[0].t_end;
+            assert!((pair[0].calc_der(t) - pair[1].calc_der(t)).abs() < 1e-12);
+        }
+        let xs = ::linspace(0.0, 6.0, 601);
+        let ys = spline.calc_sorted(&xs);
+        for w in ys.windows(2) {
+            assert!(w[1] >= w[0] - 1e-12);
+        }
+        assert!(ys.iter().all(|&y| (0.0..=3.2 + 1e-12).contains(&y)));
+
+        // The natural spline overshoots on the same data.
+        let natural = create_cubic_spline_natural(&pts).unwrap();
+        assert!(natural.calc_sorted(&xs).windows(2).any(|w| w[1] < w[0]));
+    }
+
+    #[test]
+    fn pchip_keeps_extrema() {
+        let pts = [(0.0, 1.0), (1.0, 0.0), (2.0, 0.5), (3.0, 2.0)];
+        let spline = create_pchip_spline(&pts).unwrap();
+        assert_eq!(spline.calc_der(1.0), 0.0);
+        for &x in &::linspace(0.0, 3.0, 61) {
+            assert!(spline.calc(x) >= 0.0);
+        }
+    }
+
+    #[test]
+    fn akima_spline() {
+        let pts = sample_points();
+        let spline = create_akima_spline(&pts).unwrap();
+        for &(x, y) in &pts {
+            assert!((spline.calc(x) - y).abs() < 1e-12);
+        }
+        for pair in spline.sections.windows(2) {
+            let t = pair[0].t_end;
+            assert!((pair[0].calc_der(t) - pair[1].calc_der(t)).abs() < 1e-12);
+        }
+
+        // Flat stretches of a step stay flat.
+        let step = [(0.0, 0.0), (1.0, 0.0), (2.0, 0.0), (3.0, 1.0), (4.0, 1.0), (5.0, 1.0)];
+        let spline = create_akima_spline(&step).unwrap();
+        for &x in &[0.3, 1.5, 3.5, 4.7] {
+            assert!((spline.calc(x) - spline.calc(x.floor())).abs() < 1e-12);
+        }
+
+        let line = (0..5).map(|i| (i as f64, 3.0 - 0.5 * i as f64)).collect::<Vec<_>>();
+        let spline = create_akima_spline(&line).unwrap();
+        assert!((spline.calc(2.3) - (3.0 - 0.5 * 2.3)).abs() < 1e-12);
+    }
+
+    #[test]
+    fn reproduces_straight_line() {
+        let pts = (0..6).map(|i| (i as f64, 2.0 * i as f64 - 1.0)).collect::<Vec<_>>();
+        let spline = create_cubic_spline_natural(&pts).unwrap();
+        for &x in &[0.25, 1.5, 3.3, 4.9] {
+            assert!((spline.calc(x) - (2.0 * x - 1.0)).abs() < 1e-12);
+            assert!((spline.calc_der(x) - 2.0).abs() < 1e-12);
+        }
+    }
+}
@@ -0,0 +1,308 @@
+use std::error::Error;
+use std::fmt;
+
+use rand::distributions::{IndependentSample, Normal, StudentT};
+use rand::{Rng, SeedableRng, StdRng};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SyntheticError {
+    // A parameter is not finite, a spread is negative or nu is not positive.
+    InvalidNoise(Noise),
+    // The magnitude range is not finite or not 0 <= lo <= hi.
+    InvalidMagnitude { lo: f64, hi: f64 },
+    // Outlier fraction outside [0, 1].
+    InvalidFraction { share: f64 },
+}
+
+impl fmt::Display for SyntheticError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SyntheticError::InvalidNoise(noise) => write!(f, "invalid noise parameters {:?}", noise),
+            SyntheticError::InvalidMagnitude { lo, hi } => write!(f, "invalid outlier magnitude range [{}, {})", lo, hi),
+            SyntheticError::InvalidFraction { share } => write!(f, "invalid outlier fraction {}", share),
+        }
+    }
+}
+
+impl Error for SyntheticError {}
+
+// Random error added to every model value f(x).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Noise {
+    Gaussian { sigma: f64 },
+    // scale * t with nu degrees of freedom; heavy tailed for small nu.
+    StudentT { nu: f64, scale: f64 },
+    // Uniform on [-half_width, half_width).
+    Uniform { half_width: f64 },
+    // Heteroscedastic: Gaussian with sigma = relative * |f(x)|.
+    Proportional { relative: f64 },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OutlierCount {
+    Count(usize),
+    // Share of the points, rounded to the nearest count.
+    Fraction(f64),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Sign {
+    Positive,
+    Negative,
+    // Either sign with equal probability, drawn per outlier.
+    Random,
+}
+
+// Gross errors added on top of the noise: count distinct points, each moved
+// by a magnitude drawn uniformly from [magnitude.0, magnitude.1), or by
+// exactly magnitude.0 when the bounds are equal.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Outliers {
+    pub count: OutlierCount,
+    pub magnitude: (f64, f64),
+    pub sign: Sign,
+}
+
+// Samples y = model(x) + noise at the given abscissae, with optional
+// outliers. All the randomness comes from a generator seeded with seed, so a
+// seed reproduces the data exactly.
+pub struct DataGenerator<'a> {
+    model: Box<dyn Fn(f64) -> f64 + 'a>,
+    seed: usize,
+    noise: Option<Noise>,
+    outliers: Option<Outliers>,
+}
+
+impl<'a> DataGenerator<'a> {
+    // No noise and no outliers.
+    pub fn new<F: Fn(f64) -> f64 + 'a>(model: F, seed: usize) -> DataGenerator<'a> {
+        DataGenerator {
+            model: Box::new(model),
+            seed,
+            noise: None,
+            outliers: None,
+        }
+    }
+
+    pub fn with_noise(mut self, noise: Noise) -> DataGenerator<'a> {
+        self.noise = Some(noise);
+        self
+    }
+
+    pub fn with_outliers(mut self, outliers: Outliers) -> DataGenerator<'a> {
+        self.outliers = Some(outliers);
+        self
+    }
+
+    // The noise is drawn for every point in order, then the outliers. Fails
+    // with a SyntheticError for invalid noise or outlier parameters.
+    pub fn generate(&self, xs: &[f64]) -> Result<SyntheticData, SyntheticError> {
+        self.check()?;
+        let mut rng = StdRng::from_seed(&[self.seed][..]);
+        let clean = xs.iter().map(|&x| (self.model)(x)).collect::<Vec<_>>();
+        let mut ys = clean.iter().map(|&f| f + self.noise.map_or(0.0, |noise| sample_noise(&mut rng, noise, f))).collect::<Vec<_>>();
+
+        let mut outliers = Vec::new();
+        if let Some(spec) = self.outliers {
+            let count = match spec.count {
+                OutlierCount::Count(count) => count,
+                OutlierCount::Fraction(share) => (share * xs.len() as f64).round() as usize,
+            }.min(xs.len());
+            let mut order = (0..xs.len()).collect::<Vec<_>>();
+            for j in 0..count {
+                // Partial Fisher-Yates shuffle: order[..count] is a uniform
+                // sample without repetition.
+                let pick = rng.gen_range(j, xs.len());
+                order.swap(j, pick);
+
+                let (lo, hi) = spec.magnitude;
+                let magnitude = if lo < hi { rng.gen_range(lo, hi) } else { lo };
+                let positive = match spec.sign {
+                    Sign::Positive => true,
+                    Sign::Negative => false,
+                    Sign::Random => rng.gen(),
+                };
+                ys[order[j]] += if positive { magnitude } else { -magnitude };
+            }
+            outliers = order[..count].to_vec();
+            outliers.sort();
+        }
+
+        Ok(SyntheticData { xs: xs.to_vec(), ys, clean, outliers })
+    }
+
+    fn check(&self) -> Result<(), SyntheticError> {
+        if let Some(noise) = self.noise {
+            let valid = |v: f64| v.is_finite() && v >= 0.0;
+            let ok = match noise {
+                Noise::Gaussian { sigma } => valid(sigma),
+                Noise::StudentT { nu, scale } => valid(nu) && nu > 0.0 && valid(scale),
+                Noise::Uniform { half_width } => valid(half_width),
+                Noise::Proportional { relative } => valid(relative),
+            };
+            if !ok {
+                return Err(SyntheticError::InvalidNoise(noise));
+            }
+        }
+        if let Some(spec) = self.outliers {
+            let (lo, hi) = spec.magnitude;
+            if !(lo.is_finite() && hi.is_finite() && 0.0 <= lo && lo <= hi) {
+                return Err(SyntheticError::InvalidMagnitude { lo, hi });
+            }
+            if let OutlierCount::Fraction(share) = spec.count {
+                if !(0.0..=1.0).contains(&share) {
+                    return Err(SyntheticError::InvalidFraction { share });
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn sample_noise(rng: &mut StdRng, noise: Noise, f: f64) -> f64 {
+    match noise {
+        Noise::Gaussian { sigma } => Normal::new(0.0, sigma).ind_sample(rng),
+        Noise::StudentT { nu, scale } => scale * StudentT::new(nu).ind_sample(rng),
+        Noise::Uniform { half_width } => half_width * (2.0 * rng.gen::<f64>() - 1.0),
+        Noise::Proportional { relative } => Normal::new(0.0, relative * f.abs()).ind_sample(rng),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyntheticData {
+    xs: Vec<f64>,
+    ys: Vec<f64>,
+    clean: Vec<f64>,
+    outliers: Vec<usize>,
+}
+
+impl SyntheticData {
+    pub fn xs(&self) -> &[f64] {
+        &self.xs
+    }
+
+    pub fn ys(&self) -> &[f64] {
+        &self.ys
+    }
+
+    // model(x) without noise or outliers.
+    pub fn clean(&self) -> &[f64] {
+        &self.clean
+    }
+
+    // Ground truth: indices of the injected outliers, ascending.
+    pub fn outliers(&self) -> &[usize] {
+        &self.outliers
+    }
+
+    pub fn points(&self) -> Vec<(f64, f64)> {
+        self.xs.iter().cloned().zip(self.ys.iter().cloned()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use stats::{mad, median};
+
+    fn errors(data: &SyntheticData) -> Vec<f64> {
+        data.ys().iter().zip(data.clean()).map(|(y, f)| y - f).collect()
+    }
+
+    fn sd(values: &[f64]) -> f64 {
+        let mean = values.iter().sum::<f64>() / values.len() as f64;
+        (values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (values.len() - 1) as f64).sqrt()
+    }
+
+    #[test]
+    fn seed_reproduces_data() {
+        let xs = ::linspace(0.0, 10.0, 200);
+        let generator = |seed| {
+            DataGenerator::new(|x| 2.0 * x - 1.0, seed)
+                .with_noise(Noise::Gaussian { sigma: 0.5 })
+                .with_outliers(Outliers { count: OutlierCount::Fraction(0.05), magnitude: (5.0, 8.0), sign: Sign::Random })
+        };
+        let data = generator(42).generate(&xs).unwrap();
+        assert_eq!(generator(42).generate(&xs).unwrap(), data);
+        assert!(generator(43).generate(&xs).unwrap() != data);
+        assert_eq!(data.outliers().len(), 10);
+        assert!(data.outliers().windows(2).all(|w| w[0] < w[1]));
+        assert_eq!(data.points()[7], (xs[7], data.ys()[7]));
+    }
+
+    #[test]
+    fn outlier_magnitude_and_sign() {
+        let xs = ::linspace(0.0, 1.0, 50);
+        for &(sign, expected) in &[(Sign::Positive, 1.0), (Sign::Negative, -1.0)] {
+            let data = DataGenerator::new(|x| x * x, 3)
+                .with_outliers(Outliers { count: OutlierCount::Count(7), magnitude: (10.0, 20.0), sign })
+                .generate(&xs)
+                .unwrap();
+            let e = errors(&data);
+            assert_eq!(data.outliers().len(), 7);
+            for (i, e) in e.iter().enumerate() {
+                if data.outliers().contains(&i) {
+                    assert!(e.signum() == expected && e.abs() >= 10.0 && e.abs() < 20.0);
+                } else {
+                    assert_eq!(*e, 0.0);
+                }
+            }
+        }
+
+        let data = DataGenerator::new(|x| x, 3)
+            .with_outliers(Outliers { count: OutlierCount::Count(80), magnitude: (1.0, 1.0), sign: Sign::Random })
+            .generate(&xs)
+            .unwrap();
+        assert_eq!(data.outliers().len(), 50);
+        let e = errors(&data);
+        assert!(e.iter().all(|e| (e.abs() - 1.0).abs() < 1e-15) && e.iter().any(|&e| e > 0.0) && e.iter().any(|&e| e < 0.0));
+    }
+
+    #[test]
+    fn noise_models() {
+        let xs = ::linspace(1.0, 2.0, 4000);
+        let sample = |noise| errors(&DataGenerator::new(|x| 3.0 * x, 11).with_noise(noise).generate(&xs).unwrap());
+
+        let gaussian = sample(Noise::Gaussian { sigma: 0.5 });
+        assert!((sd(&gaussian) - 0.5).abs() < 0.03);
+        assert!((1.4826 * mad(&gaussian) - 0.5).abs() < 0.03);
+
+        let uniform = sample(Noise::Uniform { half_width: 2.0 });
+        assert!(uniform.iter().all(|e| e.abs() <= 2.0));
+        assert!((sd(&uniform) - 2.0 / 3f64.sqrt()).abs() < 0.05);
+
+        // For t with 3 degrees of freedom the sd is sqrt(3) times the scale,
+        // but the outer tail is far heavier than the normal one.
+        let t = sample(Noise::StudentT { nu: 3.0, scale: 1.0 });
+        assert!(median(&t).abs() < 0.1);
+        assert!(t.iter().filter(|e| e.abs() > 4.0).count() > 4000 / 100);
+
+        let proportional = sample(Noise::Proportional { relative: 0.1 });
+        let relative = proportional.iter().zip(&xs).map(|(e, x)| e / (3.0 * x)).collect::<Vec<_>>();
+        assert!((sd(&relative) - 0.1).abs() < 0.01);
+        let (low, high) = proportional.split_at(2000);
+        assert!(sd(high) > 1.2 * sd(low));
+    }
+
+    #[test]
+    fn invalid_parameters() {
+        let xs = ::linspace(0.0, 1.0, 10);
+        let noise = |noise| DataGenerator::new(|x| x, 1).with_noise(noise).generate(&xs);
+        for &bad in &[Noise::Gaussian { sigma: -1.0 },
+                      Noise::StudentT { nu: 0.0, scale: 1.0 },
+                      Noise::Uniform { half_width: f64::NEG_INFINITY },
+                      Noise::Proportional { relative: f64::INFINITY }] {
+            assert_eq!(noise(bad), Err(SyntheticError::InvalidNoise(bad)));
+        }
+
+        let outliers = |count, magnitude| {
+            DataGenerator::new(|x| x, 1)
+                .with_outliers(Outliers { count, magnitude, sign: Sign::Random })
+                .generate(&xs)
+        };
+        assert_eq!(outliers(OutlierCount::Count(3), (5.0, 2.0)), Err(SyntheticError::InvalidMagnitude { lo: 5.0, hi: 2.0 }));
+        assert_eq!(outliers(OutlierCount::Count(3), (-1.0, 2.0)), Err(SyntheticError::InvalidMagnitude { lo: -1.0, hi: 2.0 }));
+        assert_eq!(outliers(OutlierCount::Fraction(1.5), (1.0, 2.0)), Err(SyntheticError::InvalidFraction { share: 1.5 }));
+        assert_eq!(outliers(OutlierCount::Fraction(1.0), (1.0, 2.0)).unwrap().outliers().len(), 10);
+    }
+}
@@ -1,49 +1,52 @@
 extern crate gnuplot;
-extern crate la;
-extern crate nalgebra as na;
 extern crate interp_util;
 
-use na::core::{DMatrix, MatrixVec, Dynamic};
+use std::env;
 use std::io::{BufRead, BufReader};
 use std::fs::File;
-use std::ops::IndexMut;
 use gnuplot::*;
 use interp_util::*;
+use interp_util::degree::*;
+use interp_util::orthopoly::*;
 
-fn create_col(data: Vec<f64>) -> DMatrix<f64> {
-    DMatrix::from_data(MatrixVec::new(Dynamic::new(data.len()), Dynamic::new(1), data))
+struct Series<'a> {
+    caption: &'a str,
+    x: &'a [f64],
+    y: &'a [f64],
 }
 
-fn f_col(col: &DMatrix<f64>, fun: &Fn(f64) -> f64) -> DMatrix<f64> {
-    col.map(fun)
-    //create_col(col.iter().cloned().map(fun).collect())
+// Shaded region between lo and hi along the x of the fitted line.
+struct Band<'a> {
+    caption: &'a str,
+    lo: Vec<f64>,
+    hi: Vec<f64>,
+    color: &'a str,
 }
 
-fn prod(lhs: &DMatrix<f64>, rhs: &DMatrix<f64>) -> f64 {
-    lhs.component_mul(rhs).iter().sum()
-}
-
-fn plot(plot_name: &str, name1: &str, x1: &[f64], y1: &[f64], name2: &str, x2: &[f64], y2: &[f64]) {
+fn plot(plot_name: &str, line: &Series, bands: &[Band], points: &Series) {
     let mut fg = Figure::new();
 
-    fg.axes2d()
-        .set_size(0.75, 1.0)
-        .set_title(plot_name, &[])
-        .set_x_ticks(Some((Fix(1.0), 1)), &[Mirror(false)], &[])
-        .set_y_ticks(Some((Auto, 1)), &[Mirror(false)], &[])
-        .set_legend(Graph(1.0), Graph(0.5), &[Placement(AlignLeft, AlignCenter)], &[TextAlign(AlignRight)])
-        .set_border(true, &[Left, Bottom], &[LineWidth(2.0)])
-        .set_x_label("Abscissa", &[])
-        .set_y_label("Ordinate", &[])
-        .lines(x1, y1, &[Caption(name1), LineWidth(1.5), Color("red")])
-        .points(x2, y2, &[Caption(name2), PointSymbol('x'), PointSize(1.5), Color("green")]);
-
+    {
+        let axes = fg.axes2d()
+            .set_size(0.75, 1.0)
+            .set_title(plot_name, &[])
+            .set_x_ticks(Some((Fix(1.0), 1)), &[Mirror(false)], &[])
+            .set_y_ticks(Some((Auto, 1)), &[Mirror(false)], &[])
+            .set_legend(Graph(1.0), Graph(0.5), &[Placement(AlignLeft, AlignCenter)], &[TextAlign(AlignRight)])
+            .set_border(true, &[Left, Bottom], &[LineWidth(2.0)])
+            .set_x_label("Abscissa", &[])
+            .set_y_label("Ordinate", &[]);
+        for band in bands {
+            axes.fill_between(line.x, &band.lo, &band.hi, &[Caption(band.caption), Color(band.color), FillAlpha(0.3)]);
+        }
+        axes.lines(line.x, line.y, &[Caption(line.caption), LineWidth(1.5), Color("red")])
+            .points(points.x, points.y, &[Caption(points.caption), PointSymbol('x'), PointSize(1.5), Color("green")]);
+    }
 
     fg.set_terminal("pngcairo", &format!("{}.png", plot_name));
     fg.show();
 }
 
-
 fn main() {
 
     let mut in_x = Vec::new();
@@ -63,38 +66,52 @@ fn main() {
         }
     }
 
-    let t = create_col(in_x.clone());
-    let x = create_col(in_y.clone());
-    println!("{:?}", t);
-
-    let f0 = |_: f64| { 1.0 };
-    let f1 = |x: f64| x;
-    let f2 = |x: f64| x*x;
-    let f3 = |x: f64| x*x*x;
-    let f4 = |x: f64| x*x*x*x;
-
-    let funcs: Vec<&Fn(f64) -> f64> = vec!(&f0, &f1, &f2, &f3);//, &f4);
-    let e = funcs.iter().map(|f| f_col(&t, f)).collect::<Vec<_>>();
-
-    let mut g_mat = DMatrix::identity_generic(Dynamic::new(e.len()), Dynamic::new(e.len()));
-
-    for i in 0..e.len() {
-        for j in 0..e.len() {
-            *g_mat.index_mut((i, j)) = prod(&e[i], &e[j]);
+    let data = in_x.iter().cloned().zip(in_y.iter().cloned()).collect::<Vec<_>>();
+    // Legendre polynomials of x mapped to [-1, 1] keep the fit well
+    // conditioned where the monomials on [0, 5] are not.
+    let (a, b) = data_range(&data);
+    // `task3 4` fits degree 4; `task3 aic|bic|loo|cv[K]|ftest` picks the degree
+    // by that criterion among 0..10 and prints the table, cv using K folds (10
+    // if omitted). BIC by default.
+    let arg = env::args().nth(1).unwrap_or_else(|| "bic".to_string());
+    let degree = match arg.parse::<usize>() {
+        Ok(degree) => degree,
+        Err(_) => {
+            let criterion = match arg.as_str() {
+                "aic" => Criterion::Aic,
+                "bic" => Criterion::Bic,
+                "loo" => Criterion::LeaveOneOut,
+                "ftest" => Criterion::FTest { alpha: 0.05 },
+                "cv" => Criterion::CrossValidation { folds: 10 },
+                s if s.starts_with("cv") => match s[2..].parse() {
+                    Ok(folds) => Criterion::CrossValidation { folds },
+                    Err(_) => panic!("unknown degree criterion {}", s),
+                },
+                s => panic!("unknown degree criterion {}", s),
+            };
+            let selection = select_degree(|d| legendre_basis(d, a, b), &data, None, 10, criterion)
+                .unwrap_or_else(|err| panic!("cannot select the degree by {}: {}", criterion, err));
+            println!("{}", selection);
+            selection.best_degree()
         }
+    };
+    let fit = legendre_basis(degree, a, b).fit(&data, None).unwrap();
+    println!("degree: {}", degree);
+    println!("{:>4}  {:>14}  {:>14}  {:>10}", "term", "coefficient", "std. error", "t");
+    for (j, ((c, se), t)) in fit.coefficients().iter().zip(fit.standard_errors()).zip(fit.t_statistics()).enumerate() {
+        println!("{:>4}  {:>14.6e}  {:>14.6e}  {:>10.3}", format!("P{}", j), c, se, t);
     }
-    
-    let xe: DMatrix<f64> = create_col(e.iter().map(|ei| prod(ei, &x)).collect());
-
-    let g_mat2 = la::Matrix::new(g_mat.nrows(), g_mat.ncols(), g_mat.iter().cloned().collect());
-    let xe2 = la::Matrix::new(xe.nrows(), xe.ncols(), xe.iter().cloned().collect());
-    let k = g_mat2.solve(&xe2).unwrap();
-    println!("{:?}", k);
-
-    let ft: &Fn(f64) -> f64 = &(|t| funcs.iter().map(|f| f(t)).zip(k.get_data().iter()).map(|(a, b)| a * b).sum());
-    println!("{}", ft(2.0));
+    println!("R^2: {}", fit.r_squared());
+    println!("rank: {}, condition number: {:e}", fit.rank(), fit.condition_number());
 
     let space = linspace(*in_x.first().unwrap(), *in_x.last().unwrap(), 300);
-    let f_space = space.iter().cloned().map(ft).collect::<Vec<_>>();
-    plot("Task3", "Min square fit", &space, &f_space, "Points", &in_x, &in_y)
+    let f_space = space.iter().map(|&x| fit.calc(x)).collect::<Vec<_>>();
+    let (conf_lo, conf_hi) = space.iter().map(|&x| fit.confidence_band(x, 0.95)).unzip();
+    let (pred_lo, pred_hi) = space.iter().map(|&x| fit.prediction_band(x, 0.95)).unzip();
+    let bands = [Band { caption: "95% prediction band", lo: pred_lo, hi: pred_hi, color: "light-blue" },
+                 Band { caption: "95% confidence band", lo: conf_lo, hi: conf_hi, color: "blue" }];
+    plot("Task3",
+         &Series { caption: "Min square fit", x: &space, y: &f_space },
+         &bands,
+         &Series { caption: "Points", x: &in_x, y: &in_y })
 }
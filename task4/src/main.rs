@@ -1,30 +1,19 @@
 extern crate interp_util;
-extern crate rand;
 extern crate gnuplot;
-extern crate statistics;
+
+use std::env;
 
 use gnuplot::*;
 use interp_util::*;
-use rand::distributions::{IndependentSample, Normal};
-use statistics::*;
-use rand::Rng;
-
-fn generate_function(pts: &Vec<f64>, a: f64, b: f64, err: f64) -> Vec<f64> {
-    let mut rng = rand::thread_rng();
-    let normal = Normal::new(0.0, err);
-    pts.iter().map(|x| (a*x + b + normal.ind_sample(&mut rng))).collect()
-}
-
-fn calc_derivative(xs: &[f64], ys: &[f64]) -> Vec<f64> {
-    let mut res = Vec::new();
-    for i in 1..xs.len() - 1 {
-        let t = xs[i+1]- xs[i-1];
-        res.push((ys[i-1] + ys[i+1] - 2.0 * ys[i]) / (t*t));
-    }
-    res
-}
+use interp_util::diff::{second_derivative, Accuracy};
+use interp_util::lsq::polynomial_basis;
+use interp_util::median_line::{repeated_median, theil_sen};
+use interp_util::outlier::{detect, evaluate, Detector};
+use interp_util::ransac::Ransac;
+use interp_util::robust::{fit_robust, Loss};
+use interp_util::synthetic::{DataGenerator, Noise, OutlierCount, Outliers, Sign};
 
-fn plot_line_data(a: f64, b: f64, x: &[f64], y: &[f64], der_x: &[f64], der_y: &[f64]) {
+fn plot_line_data(a: f64, b: f64, fitted: (f64, f64), x: &[f64], y: &[f64], der_x: &[f64], der_y: &[f64]) {
     let first_pt = x[0];
     let last_pt = x[x.len() - 1];
     let mut fg = Figure::new();
@@ -33,10 +22,12 @@ fn plot_line_data(a: f64, b: f64, x: &[f64], y: &[f64], der_x: &[f64], der_y: &[
         .set_legend(Graph(0.5), Graph(1.0), &[], &[])
         .set_x_ticks(Some((Auto, 1)), &[Mirror(false)], &[])
         .set_y_ticks(Some((Auto, 1)), &[Mirror(false)], &[])
-        .boxes(der_x, der_y, &[Caption("Derivative"), Color("gray")])
+        .boxes(der_x, der_y, &[Caption("Second derivative"), Color("gray")])
         .points(x, y, &[Caption("Function with erros"), PointSize(1.0), Color("red")])
-        .lines(&[first_pt, last_pt], &[a * first_pt + b, a * last_pt + b],
-            &[Caption("Reference function"), LineWidth(1.5), Color("green")]);
+        .lines([first_pt, last_pt], [a * first_pt + b, a * last_pt + b],
+            &[Caption("Reference function"), LineWidth(1.5), Color("green")])
+        .lines([first_pt, last_pt], [fitted.0 * first_pt + fitted.1, fitted.0 * last_pt + fitted.1],
+            &[Caption("Robust fit"), LineWidth(1.5), Color("blue")]);
 
     fg.set_terminal("pngcairo", "line_data.png");
     fg.show();
@@ -46,49 +37,72 @@ fn main() {
     let a = 1.5;
     let b = 1.0;
     let err_sigma = 3.0;
+    // The first argument seeds the generator, so a run can be repeated.
+    let seed = env::args().nth(1).map_or(0, |arg| arg.parse().expect("seed must be a non-negative integer"));
 
     println!("f(x) = {}*x + {}", a, b);
     println!("Default error: {}", err_sigma);
+    println!("Seed: {}", seed);
     let outlier_num = 3;
     let xs = linspace(0.0, 100.0, 100);
-    let mut ys = generate_function(&xs, a, b, err_sigma);
-    for i in 0..outlier_num {
-        let mut rng = rand::thread_rng();
-        let idx = rng.gen_range::<usize>(0, ys.len());
-        let err = rng.gen_range(err_sigma * 20.0, err_sigma * 30.0) * (2 * rng.gen_range::<i32>(0, 2) - 1) as f64;
-        println!("Outlier #{}: {}, error: {}", i + 1, idx, err);
-        ys[idx] += err;
+    let data = DataGenerator::new(|x| a * x + b, seed)
+        .with_noise(Noise::Gaussian { sigma: err_sigma })
+        .with_outliers(Outliers {
+            count: OutlierCount::Count(outlier_num),
+            magnitude: (err_sigma * 20.0, err_sigma * 30.0),
+            sign: Sign::Random,
+        })
+        .generate(&xs)
+        .unwrap();
+    let ys = data.ys().to_vec();
+    let truth = data.outliers().to_vec();
+    for (i, &idx) in truth.iter().enumerate() {
+        println!("Outlier #{}: {}, error: {}", i + 1, idx, ys[idx] - data.clean()[idx]);
     }
-    let der = calc_derivative(&xs, &ys);
-
-
-    let der_mean = mean(&der);
-    let der_variance = variance(&der);
-    println!("Derivative mean: {}\nDerivative variance: {}", der_mean, der_variance);
+    let der = second_derivative(&xs, &ys, Accuracy::Second).unwrap();
 
-    for (i, d) in der.iter().enumerate() {
-        if (d - der_mean).abs() > 3.0 * der_variance {
-            println!("Possible outlier: {}", i + 1);
+    let pts = data.points();
+    let mut fitted = (a, b);
+    let mut residuals = Vec::new();
+    for &(name, loss) in &[("Huber", Loss::huber()), ("Tukey", Loss::tukey()), ("Cauchy", Loss::cauchy())] {
+        let fit = fit_robust(&polynomial_basis(1), &pts, None, loss).unwrap();
+        let c = fit.coefficients();
+        println!("{} fit: f(x) = {}*x + {} (scale {}, {} iterations)", name, c[1], c[0], fit.scale(), fit.iterations());
+        if let Loss::Tukey { .. } = loss {
+            // Bisquare weights drop to zero beyond c*scale.
+            for i in fit.outliers(f64::MIN_POSITIVE) {
+                println!("Rejected by the robust fit: point #{}, residual {}", i, fit.residuals()[i]);
+            }
+            fitted = (c[1], c[0]);
+            residuals = fit.residuals().to_vec();
         }
     }
 
-    plot_line_data(a, b, &xs, &ys, &xs[1 .. xs.len() - 1], &der);
-
-    let outlier_ratio = 0.05;
-    let target_outlier_num = (outlier_ratio * xs.len() as f64) as usize;
-    
-    let mut max_variance = 3.0 * der_variance;
-    loop {
-        let mut outliers = der.iter().enumerate().filter(|&(_, x)| x.abs() > max_variance).collect::<Vec<_>>();
-
-        if outliers.len() >= target_outlier_num {
-            outliers.sort_by(|&(_, a), &(_, b)| b.abs().partial_cmp(&a.abs()).unwrap());
-            for &(i, x) in outliers.iter() {
-                println!("Possible outlier: point #{}, derivative {}", i + 1, x);
-            }
+    for &detector in &[Detector::z_score(),
+                       Detector::modified_z(),
+                       Detector::hampel(),
+                       Detector::grubbs(),
+                       Detector::generalized_esd(residuals.len()),
+                       Detector::Chauvenet] {
+        let flagged = detect(&residuals, detector).unwrap();
+        let eval = evaluate(&flagged, &truth);
+        println!("{}: {} (precision {:.2}, recall {:.2})", detector,
+                 flagged.iter().map(|o| format!("#{} ({:.1})", o.index, o.score)).collect::<Vec<_>>().join(", "),
+                 eval.precision(), eval.recall());
+    }
 
-            break;
-        }
-        max_variance *= 0.99;
+    // Points further than three sigma from the line count as outliers.
+    let threshold = 3.0 * err_sigma;
+    let ransac = Ransac::new(polynomial_basis(1), threshold).with_seed(1).fit(&pts).unwrap();
+    println!("RANSAC: f(x) = {}*x + {}, {} inliers, outliers {:?}",
+             ransac.coefficients()[1], ransac.coefficients()[0], ransac.inliers().len(),
+             (0..pts.len()).filter(|i| !ransac.inliers().contains(i)).collect::<Vec<_>>());
+    for &(name, ref line) in &[("Theil-Sen", theil_sen(&pts).unwrap()), ("Repeated median", repeated_median(&pts).unwrap())] {
+        let inliers = line.inliers(threshold);
+        println!("{}: f(x) = {}*x + {}, {} inliers, outliers {:?}",
+                 name, line.slope(), line.intercept(), inliers.len(),
+                 (0..pts.len()).filter(|i| !inliers.contains(i)).collect::<Vec<_>>());
     }
+
+    plot_line_data(a, b, fitted, &xs, &ys, &xs, &der);
 }